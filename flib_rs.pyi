@@ -1,10 +1,195 @@
-from typing import List, Tuple, Optional
+from typing import Dict, List, Literal, Optional, Tuple
+
+class Author:
+    last_name: str
+    first_name: str
+    middle_name: str
+    name: str
+    key: str
+
+class AuthorCount:
+    author: Author
+    count: int
+
+class Book:
+    id: int
+    authors: List[Author]
+    book_title: str
+    genres: List[str]
+    series: str
+    serno: int
+    size: int
+    libid: int
+    deleted: bool
+    ext: str
+    date: str
+    lang: str
+    rating: int
+    keywords: str
+    zip_archive: str
+
+class Snippet:
+    text: str
+    ranges: List[Tuple[int, int]]
+    marked: str
+
+class SearchHit:
+    book: Book
+    score: float
+    snippets: Dict[str, Snippet]
+
+class SearchPage:
+    hits: List[SearchHit]
+    total: int
+    offset: int
+    limit: int
+    facets: Dict[str, Dict[str, int]]
+    suggestion: Optional[str]
+    @property
+    def has_more(self) -> bool: ...
+    def __len__(self) -> int: ...
+
+class Genre:
+    code: str
+    name_ru: str
+    name_en: str
+    count: int
+
+class GenreGroup:
+    code: str
+    name_ru: str
+    name_en: str
+    count: int
+    genres: List[Genre]
+
+class Series:
+    name: str
+    count: int
+    authors: List[str]
+
+class SeriesBooks:
+    name: str
+    books: List[Book]
+
+class Suggestion:
+    text: str
+    key: str
+    count: int
+
+class Bibliography:
+    author: Optional[Author]
+    series: List[SeriesBooks]
+    standalone: List[Book]
+
+class BuildReport:
+    indexed: int
+    added: int
+    updated: int
+    removed: int
+    unchanged: int
+    kept: int
+    too_few_fields: int
+    invalid_id: int
+    missing_archive: int
+    duplicate_id: int
+    missing_archives: List[str]
+    duplicate_ids: List[int]
+    unreadable_files: List[str]
+    bad_lines: List[Tuple[str, str, str]]
+    elapsed_secs: float
+    @property
+    def skipped(self) -> int: ...
+
+class BookDeletedError(RuntimeError): ...
+
+class QuerySyntaxError(ValueError):
+    query: str
+    position: Optional[int]
+    reason: str
 
 class FlibRS:
-    def __init__(self, index_path: str, zip_archives_dir: Optional[str] = None) -> None: ...
+    def __init__(
+        self,
+        index_path: str,
+        zip_archives_dir: Optional[str] = None,
+        reload_policy: Literal["on_commit", "manual"] = "on_commit",
+    ) -> None: ...
     def index_exists(self) -> bool: ...
-    def build_index(self, inpx_path: str) -> None: ...
-    def search(self, query: str) -> List[Tuple[int, str, str, float]]: ...
-    def get_info(self, id: int) -> Tuple[str, str]: ...
+    def reload(self) -> None: ...
+    def close(self) -> None: ...
+    def build_index(
+        self, inpx_path: str, bad_lines_limit: int = 0, incremental: bool = False
+    ) -> BuildReport: ...
+    def search(
+        self,
+        query: str,
+        include_deleted: bool = False,
+        limit: int = 10,
+        offset: int = 0,
+        fuzzy: bool = False,
+        translit: bool = True,
+        strict: bool = False,
+        facets: bool = False,
+        langs: Optional[List[str]] = None,
+        exts: Optional[List[str]] = None,
+        date_from: Optional[str] = None,
+        date_to: Optional[str] = None,
+        size_min: Optional[int] = None,
+        size_max: Optional[int] = None,
+        min_rating: Optional[int] = None,
+        sort: Literal[
+            "relevance", "date", "title", "author", "serno", "size", "rating"
+        ] = "relevance",
+        reverse: bool = False,
+        highlight: bool = False,
+        markers: Tuple[str, str] = ("<b>", "</b>"),
+    ) -> SearchPage: ...
+    def search_by(
+        self,
+        author: Optional[str] = None,
+        title: Optional[str] = None,
+        series: Optional[str] = None,
+        genre: Optional[str] = None,
+        lang: Optional[str] = None,
+        genre_group: Optional[str] = None,
+        include_deleted: bool = False,
+        limit: int = 10,
+        offset: int = 0,
+        fuzzy: bool = False,
+        translit: bool = True,
+        facets: bool = False,
+        langs: Optional[List[str]] = None,
+        exts: Optional[List[str]] = None,
+        date_from: Optional[str] = None,
+        date_to: Optional[str] = None,
+        size_min: Optional[int] = None,
+        size_max: Optional[int] = None,
+        min_rating: Optional[int] = None,
+        sort: Literal[
+            "relevance", "date", "title", "author", "serno", "size", "rating"
+        ] = "relevance",
+        reverse: bool = False,
+        highlight: bool = False,
+        markers: Tuple[str, str] = ("<b>", "</b>"),
+    ) -> SearchPage: ...
+    def get_info(self, id: int) -> Book: ...
     def download(self, id: int) -> bool: ...
-    def get_file_bytes(self, id: int) -> bytes: ...
+    def get_file_bytes(self, id: int) -> Tuple[bytes, str]: ...
+    def series(self, name: str, include_deleted: bool = False) -> List[Book]: ...
+    def series_search(
+        self, query: str, limit: int = 10, include_deleted: bool = False
+    ) -> List[Series]: ...
+    def books_by_author(
+        self, author_key: str, include_deleted: bool = False
+    ) -> Bibliography: ...
+    def authors(
+        self, prefix: str = "", limit: int = 50, offset: int = 0
+    ) -> List[AuthorCount]: ...
+    def suggest(
+        self,
+        prefix: str,
+        kind: Literal["author", "title", "series"] = "author",
+        limit: int = 10,
+        include_deleted: bool = False,
+    ) -> List[Suggestion]: ...
+    def genres(self) -> List[GenreGroup]: ...
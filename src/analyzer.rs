@@ -0,0 +1,123 @@
+//! Анализатор текста для полей автора и названия: приведение к нижнему регистру,
+//! замена Ё на Е и стемминг (русский Snowball для кириллицы, английский для латиницы)
+
+use tantivy::tokenizer::{
+    BoxTokenStream, Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer, TextAnalyzer,
+    Token, TokenFilter, TokenStream,
+};
+use tantivy::Index;
+
+/// Имя анализатора в `TokenizerManager` индекса
+pub(crate) const RU_STEM: &str = "ru_stem";
+
+/// Анализатор словаря для исправления опечаток: те же слова, но без стемминга
+pub(crate) const SPELL: &str = "spell";
+
+/// Замена `ё` на `е`: в INPX и в запросах пользователей буква используется непоследовательно
+#[derive(Clone)]
+pub(crate) struct YoFolding;
+
+impl TokenFilter for YoFolding {
+    fn transform<'a>(&self, token_stream: BoxTokenStream<'a>) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(YoFoldingTokenStream { tail: token_stream })
+    }
+}
+
+pub(crate) struct YoFoldingTokenStream<'a> {
+    tail: BoxTokenStream<'a>,
+}
+
+impl<'a> TokenStream for YoFoldingTokenStream<'a> {
+    fn advance(&mut self) -> bool {
+        if !self.tail.advance() {
+            return false;
+        }
+        let text = &mut self.tail.token_mut().text;
+        if text.contains(['ё', 'Ё']) {
+            *text = text.replace('ё', "е").replace('Ё', "Е");
+        }
+        true
+    }
+
+    fn token(&self) -> &Token {
+        self.tail.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.tail.token_mut()
+    }
+}
+
+/// Анализатор для русского текста. Русский стеммер не трогает латиницу, а английский -
+/// кириллицу, поэтому цепочка из двух стеммеров обрабатывает каждое слово по его алфавиту
+pub(crate) fn ru_analyzer() -> TextAnalyzer {
+    TextAnalyzer::from(SimpleTokenizer)
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+        .filter(YoFolding)
+        .filter(Stemmer::new(Language::Russian))
+        .filter(Stemmer::new(Language::English))
+}
+
+/// Слова в том виде, в каком их предлагают пользователю: нижний регистр, Ё -> Е
+pub(crate) fn spell_analyzer() -> TextAnalyzer {
+    TextAnalyzer::from(SimpleTokenizer)
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+        .filter(YoFolding)
+}
+
+/// Регистрация анализаторов в индексе; нужна при каждом открытии, т.к. они не сохраняются на диск
+pub(crate) fn register_tokenizers(index: &Index) {
+    index.tokenizers().register(RU_STEM, ru_analyzer());
+    index.tokenizers().register(SPELL, spell_analyzer());
+}
+
+/// Термы, в которые анализатор превращает текст (для построения запросов вручную)
+pub(crate) fn analyze(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    ru_analyzer()
+        .token_stream(text)
+        .process(&mut |token: &Token| terms.push(token.text.clone()));
+    terms
+}
+
+/// Слова текста для поля `spell` вместе с байтовыми смещениями в исходном тексте
+pub(crate) fn spell_tokens(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    spell_analyzer()
+        .token_stream(text)
+        .process(&mut |token: &Token| tokens.push(token.clone()));
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn russian_word_forms_share_a_stem() {
+        assert_eq!(analyze("войны"), analyze("Война"));
+        assert_eq!(analyze("Толстого"), analyze("Толстой"));
+        assert_eq!(analyze("Война и мир"), analyze("войны и мира"));
+    }
+
+    #[test]
+    fn yo_is_folded_to_ye() {
+        assert_eq!(analyze("Ёлка"), analyze("елка"));
+        assert_eq!(analyze("Пётр"), analyze("ПЕТР"));
+        let words: Vec<String> = spell_tokens("Мёртвые ДУШИ")
+            .into_iter()
+            .map(|t| t.text)
+            .collect();
+        assert_eq!(words, ["мертвые", "души"]);
+    }
+
+    #[test]
+    fn latin_words_get_english_stems() {
+        assert_eq!(analyze("Wars"), ["war"]);
+        assert_eq!(analyze("running"), analyze("runs"));
+        // Латиница проходит мимо русского стеммера, кириллица - мимо английского
+        assert_eq!(analyze("Star Wars Войны"), ["star", "war", "войн"]);
+    }
+}
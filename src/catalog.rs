@@ -0,0 +1,170 @@
+//! Алфавитный каталог авторов для навигации «Авторы -> Б -> Бу -> Булгаков».
+//! Строится из разобранных книг при сборке индекса и хранится рядом с ним
+//! в `authors.bin` (bincode): перечислять авторов через индекс пришлось бы
+//! с чтением всех документов
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::{collation, AuthorCount, Book};
+
+/// Имя файла каталога в каталоге индекса
+pub(crate) const CATALOG_FILE: &str = "authors.bin";
+
+/// Авторы, отсортированные по фамилии, имени и отчеству
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct AuthorCatalog {
+    entries: Vec<AuthorCount>,
+}
+
+impl AuthorCatalog {
+    /// Каталог по книгам INPX; написание имени берётся из первой книги автора
+    pub(crate) fn from_books<'a>(books: impl IntoIterator<Item = &'a Book>) -> Self {
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        let mut entries: Vec<AuthorCount> = Vec::new();
+        for author in books
+            .into_iter()
+            .filter(|book| !book.deleted)
+            .flat_map(|book| &book.authors)
+        {
+            match positions.get(author.key.as_str()) {
+                Some(&i) => entries[i].count += 1,
+                None => {
+                    positions.insert(&author.key, entries.len());
+                    entries.push(AuthorCount {
+                        author: author.clone(),
+                        count: 1,
+                    });
+                }
+            }
+        }
+        entries.sort_by(|a, b| {
+            collation::compare(&a.author.key, &b.author.key)
+                .then_with(|| a.author.key.cmp(&b.author.key))
+        });
+        AuthorCatalog { entries }
+    }
+
+    /// Запись каталога рядом с индексом; через временный файл, чтобы читатели
+    /// не увидели его наполовину записанным
+    pub(crate) fn save(&self, index_path: &Path) -> Result<(), Box<dyn Error>> {
+        let path = index_path.join(CATALOG_FILE);
+        let tmp_path = index_path.join(format!("{}.tmp", CATALOG_FILE));
+        fs::write(&tmp_path, bincode::serialize(self)?)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    /// Время изменения файла каталога: по нему видно, что индекс пересобран другим процессом
+    pub(crate) fn modified(index_path: &Path) -> Result<SystemTime, Box<dyn Error>> {
+        let path = index_path.join(CATALOG_FILE);
+        let metadata = fs::metadata(&path).map_err(|e| {
+            format!(
+                "Не удалось прочитать каталог авторов '{}': {}, пересоздайте индекс",
+                path.display(),
+                e
+            )
+        })?;
+        Ok(metadata.modified()?)
+    }
+
+    pub(crate) fn load(index_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = index_path.join(CATALOG_FILE);
+        let bytes = fs::read(&path).map_err(|e| {
+            format!(
+                "Не удалось прочитать каталог авторов '{}': {}, пересоздайте индекс",
+                path.display(),
+                e
+            )
+        })?;
+        Ok(bincode::deserialize(&bytes)?)
+    }
+
+    /// Страница авторов, чья фамилия (ключ `фамилия имя отчество`) начинается с `prefix`
+    pub(crate) fn page(&self, prefix: &str, limit: usize, offset: usize) -> Vec<AuthorCount> {
+        let prefix = collation::normalize(prefix);
+        self.entries
+            .iter()
+            .filter(|entry| entry.author.key.starts_with(&prefix))
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{self, inp_line};
+
+    #[test]
+    fn authors_in_catalog_order_with_live_counts() {
+        let lines = [
+            inp_line(
+                1,
+                "Булгаков,Михаил,Афанасьевич:",
+                "Мастер и Маргарита",
+                "",
+                0,
+            ),
+            inp_line(2, "Булгаков,Михаил,Афанасьевич:", "Белая гвардия", "", 0),
+            testing::with_column(
+                &inp_line(3, "Булгаков,Михаил,Афанасьевич:", "Черновик", "", 0),
+                "DEL",
+                "1",
+            ),
+            inp_line(4, "Бунин,Иван,Алексеевич:", "Тёмные аллеи", "", 0),
+            inp_line(5, "Белый,Андрей,:", "Петербург", "", 0),
+            inp_line(6, "Ёлкин,Пётр,:", "Ёлка", "", 0),
+            inp_line(7, "Еремеев,Илья,:", "Ерш", "", 0),
+            inp_line(8, "Жуков,Олег,:Блок,Александр,:", "Стихи", "", 0),
+            inp_line(9, "Asimov,Isaac,:", "I, Robot", "", 0),
+            testing::with_column(&inp_line(10, "Удалов,Иван,:", "Нет", "", 0), "DEL", "1"),
+        ];
+        let (_dir, idx) = testing::index_with("author_catalog", &lines);
+        let catalog = idx.author_catalog().unwrap();
+        let page = |prefix: &str, limit, offset| {
+            catalog
+                .page(prefix, limit, offset)
+                .into_iter()
+                .map(|entry| (entry.author.last_name, entry.count))
+                .collect::<Vec<_>>()
+        };
+        let names = |prefix: &str, limit, offset| {
+            page(prefix, limit, offset)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+        };
+
+        // Латиница перед кириллицей, Ё рядом с Е, авторы только удалённых книг не попадают
+        assert_eq!(
+            names("", 50, 0),
+            [
+                "Asimov",
+                "Белый",
+                "Блок",
+                "Булгаков",
+                "Бунин",
+                "Ёлкин",
+                "Еремеев",
+                "Жуков"
+            ]
+        );
+        // Сужение по префиксу без учёта регистра и Ё
+        assert_eq!(names("Б", 50, 0), ["Белый", "Блок", "Булгаков", "Бунин"]);
+        assert_eq!(names("бу", 50, 0), ["Булгаков", "Бунин"]);
+        assert_eq!(page("Булгаков", 50, 0), [("Булгаков".to_string(), 2)]);
+        assert_eq!(names("ё", 50, 0), ["Ёлкин", "Еремеев"]);
+        assert_eq!(names("елк", 50, 0), ["Ёлкин"]);
+        assert!(names("Удалов", 50, 0).is_empty());
+        // Страницы
+        assert_eq!(names("", 2, 1), ["Белый", "Блок"]);
+        assert_eq!(names("Б", 10, 3), ["Бунин"]);
+        assert!(names("Б", 10, 4).is_empty());
+    }
+}
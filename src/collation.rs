@@ -0,0 +1,149 @@
+//! Порядок сортировки строк для русскоязычного каталога: цифры, латиница, затем
+//! кириллица по алфавиту (Ё наравне с Е), регистр и знаки препинания не учитываются
+
+use std::cmp::Ordering;
+
+/// Кириллический алфавит без Ё
+const CYRILLIC: &str = "абвгдежзийклмнопрстуфхцчшщъыьэюя";
+
+/// Вес разделителя слов: «Дом» идёт раньше «Дома» и «Дом у дороги» раньше «Домовой»
+const SEPARATOR: u8 = 1;
+
+/// Вес символа при сравнении, `None` для игнорируемых символов
+fn weight(c: char) -> Option<u8> {
+    match c {
+        '0'..='9' => Some(2 + (c as u8 - b'0')),
+        'a'..='z' => Some(12 + (c as u8 - b'a')),
+        'ё' => weight('е'),
+        _ => match CYRILLIC.chars().position(|l| l == c) {
+            Some(i) => Some(38 + i as u8),
+            // Прочие буквы - после всех известных алфавитов
+            None if c.is_alphanumeric() => Some(127),
+            None => None,
+        },
+    }
+}
+
+/// Последовательность весов строки; слова разделяются одним `SEPARATOR`
+fn weights(text: &str) -> Vec<u8> {
+    let mut out: Vec<u8> = Vec::new();
+    let mut pending_separator = false;
+    for c in text.chars().flat_map(char::to_lowercase) {
+        match weight(c) {
+            Some(w) => {
+                if pending_separator && !out.is_empty() {
+                    out.push(SEPARATOR);
+                }
+                pending_separator = false;
+                out.push(w);
+            }
+            None => pending_separator = true,
+        }
+    }
+    out
+}
+
+/// Ключ сортировки для u64 fast-поля: первые 9 символов по 7 бит.
+/// Порядок ключей совпадает с порядком строк с точностью до этих 9 символов,
+/// книги с равными ключами досортировываются через `compare`; пустая строка даёт 0
+pub(crate) fn sort_key(text: &str) -> u64 {
+    let mut key = 0u64;
+    let weights = weights(text);
+    for i in 0..9 {
+        key = (key << 7) | u64::from(weights.get(i).copied().unwrap_or(0));
+    }
+    key
+}
+
+/// Нормализованный ключ каталога: нижний регистр, Ё -> Е, пробелы схлопнуты.
+/// По нему сравниваются серии и авторы, записанные в INPX по-разному
+pub(crate) fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| word.to_lowercase().replace('ё', "е"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Полное сравнение строк в порядке каталога
+pub(crate) fn compare(a: &str, b: &str) -> Ordering {
+    weights(a).cmp(&weights(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digits_latin_then_cyrillic() {
+        assert_eq!(compare("1984", "Hobbit"), Ordering::Less);
+        assert_eq!(compare("Hobbit", "Азазель"), Ordering::Less);
+        assert_eq!(compare("Яма", "Ωμέγα"), Ordering::Less);
+    }
+
+    #[test]
+    fn case_yo_and_punctuation_are_ignored() {
+        assert_eq!(compare("Ёлка", "елка"), Ordering::Equal);
+        assert_eq!(compare("«Война и мир»", "война  и мир"), Ordering::Equal);
+        assert_eq!(compare("Ёж", "Жук"), Ordering::Less);
+    }
+
+    #[test]
+    fn shorter_word_sorts_first() {
+        assert_eq!(compare("Дом", "Дома"), Ordering::Less);
+        assert_eq!(compare("Дом у дороги", "Дома"), Ordering::Less);
+        assert_eq!(compare("Дом у дороги", "Домовой"), Ordering::Less);
+    }
+
+    #[test]
+    fn sort_key_follows_compare() {
+        let titles = [
+            "1984",
+            "Hobbit",
+            "Дом",
+            "Дом у дороги",
+            "Дома",
+            "Ёлка",
+            "Яма",
+        ];
+        for pair in titles.windows(2) {
+            assert!(sort_key(pair[0]) < sort_key(pair[1]), "{:?}", pair);
+        }
+        assert_eq!(sort_key("Ёлка"), sort_key("елка"));
+    }
+
+    #[test]
+    fn sort_key_never_contradicts_compare() {
+        // Ключ различает первые 9 символов, дальше порядок уточняется через `compare`
+        let titles = [
+            "абвгдежз",
+            "абвгдежзи",
+            "Приключени",
+            "Приключения",
+            "абвгдежзк",
+            "Дом",
+        ];
+        for a in titles {
+            for b in titles {
+                if compare(a, b) == Ordering::Less {
+                    assert!(sort_key(a) <= sort_key(b), "{} < {}", a, b);
+                }
+            }
+        }
+        assert_eq!(sort_key("Приключения Тома"), sort_key("Приключения Гека"));
+        assert!(sort_key("абвгдежз") < sort_key("абвгдежзи"));
+    }
+
+    #[test]
+    fn empty_text_has_zero_key() {
+        assert_eq!(sort_key(""), 0);
+        assert_eq!(sort_key(" «…» "), 0);
+        assert!(sort_key("0") > 0);
+    }
+
+    #[test]
+    fn normalize_folds_case_yo_and_spaces() {
+        assert_eq!(normalize("  Ночной   ДОЗОР "), "ночной дозор");
+        assert_eq!(normalize("Ёжиков Пётр"), "ежиков петр");
+        assert_eq!(normalize(""), "");
+    }
+}
@@ -0,0 +1,156 @@
+//! Подсчёт найденных книг по жанрам, языкам, форматам и десятилетиям даты
+//! поступления - для сужения больших выдач. Считается за тот же проход, что и страница
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tantivy::collector::{Collector, FacetCollector, FacetCounts, SegmentCollector};
+use tantivy::fastfield::Column;
+use tantivy::schema::{Facet, Field};
+use tantivy::{DateTime, DocId, Score, SegmentOrdinal, SegmentReader};
+
+use crate::genres;
+use crate::BookFields;
+
+/// Число книг по десятилетиям даты: первый год десятилетия -> число книг
+pub(crate) struct DecadeCollector {
+    field: Field,
+}
+
+pub(crate) struct DecadeSegmentCollector {
+    dates: Arc<dyn Column<DateTime>>,
+    counts: HashMap<i32, u64>,
+}
+
+impl Collector for DecadeCollector {
+    type Fruit = HashMap<i32, u64>;
+    type Child = DecadeSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<DecadeSegmentCollector> {
+        Ok(DecadeSegmentCollector {
+            dates: segment.fast_fields().date(self.field)?,
+            counts: HashMap::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<HashMap<i32, u64>>) -> tantivy::Result<Self::Fruit> {
+        let mut counts = HashMap::new();
+        for fruit in segment_fruits {
+            for (decade, count) in fruit {
+                *counts.entry(decade).or_insert(0) += count;
+            }
+        }
+        Ok(counts)
+    }
+}
+
+impl SegmentCollector for DecadeSegmentCollector {
+    type Fruit = HashMap<i32, u64>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        let date = self.dates.get_val(doc);
+        // Книги без даты получают в fast-поле нулевое значение (1970-01-01), их не считаем
+        if date.into_timestamp_secs() == 0 {
+            return;
+        }
+        let year = date.into_utc().year();
+        *self.counts.entry(year - year.rem_euclid(10)).or_insert(0) += 1;
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.counts
+    }
+}
+
+/// Коллекторы всех фасетов: (группы жанров, жанры), язык, формат, десятилетие
+pub(crate) type FacetsCollector = (
+    (FacetCollector, FacetCollector),
+    FacetCollector,
+    FacetCollector,
+    DecadeCollector,
+);
+
+pub(crate) type FacetsFruit = (
+    (FacetCounts, FacetCounts),
+    FacetCounts,
+    FacetCounts,
+    HashMap<i32, u64>,
+);
+
+/// Коллектор первого уровня фасетного поля
+fn root_collector(field: Field) -> FacetCollector {
+    let mut collector = FacetCollector::for_field(field);
+    collector.add_facet(Facet::root());
+    collector
+}
+
+/// Коллекторы групп жанров и жанров внутри каждой группы.
+/// Фасет и его потомков нельзя добавить в один коллектор, поэтому их два
+pub(crate) fn genre_collectors(field: Field) -> (FacetCollector, FacetCollector) {
+    let mut genres_collector = FacetCollector::for_field(field);
+    for group in genres::GENRE_GROUPS {
+        genres_collector.add_facet(Facet::from_path([group.code]));
+    }
+    (root_collector(field), genres_collector)
+}
+
+/// Коллекторы фасетов для полей индекса
+pub(crate) fn facets_collector(fields: &BookFields) -> FacetsCollector {
+    (
+        genre_collectors(fields.genre),
+        root_collector(fields.lang),
+        root_collector(fields.ext),
+        DecadeCollector { field: fields.date },
+    )
+}
+
+/// Последний сегмент пути фасета: код жанра, языка или формата
+fn facet_code(facet: &Facet) -> String {
+    facet
+        .to_path()
+        .last()
+        .map(|s| s.to_string())
+        .unwrap_or_default()
+}
+
+/// Счётчики всех значений под фасетом `parent`
+fn counts_under(counts: &FacetCounts, parent: Facet) -> HashMap<String, u64> {
+    counts
+        .get(parent)
+        .map(|(facet, count)| (facet_code(facet), count))
+        .collect()
+}
+
+/// Результат коллекторов в виде `{"genre": {"sf_fantasy": 3, ...}, "genre_group": ..., ...}`
+pub(crate) fn facet_counts(fruit: FacetsFruit) -> HashMap<String, HashMap<String, u64>> {
+    let ((group_counts, genre_counts), lang_counts, ext_counts, decades) = fruit;
+    let mut genre = HashMap::new();
+    for group in genres::GENRE_GROUPS {
+        genre.extend(counts_under(&genre_counts, Facet::from_path([group.code])));
+    }
+    let decade = decades
+        .into_iter()
+        .map(|(decade, count)| (decade.to_string(), count))
+        .collect();
+
+    HashMap::from([
+        ("genre".to_string(), genre),
+        (
+            "genre_group".to_string(),
+            counts_under(&group_counts, Facet::root()),
+        ),
+        (
+            "lang".to_string(),
+            counts_under(&lang_counts, Facet::root()),
+        ),
+        ("ext".to_string(), counts_under(&ext_counts, Facet::root())),
+        ("decade".to_string(), decade),
+    ])
+}
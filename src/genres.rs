@@ -0,0 +1,375 @@
+//! Справочник жанров FB2: группы верхнего уровня и коды жанров с названиями
+//! на русском и английском (по спискам Flibusta / MyHomeLib)
+
+use tantivy::schema::Facet;
+
+/// Группа жанров верхнего уровня
+pub(crate) struct GenreGroupInfo {
+    pub code: &'static str,
+    pub name_ru: &'static str,
+    pub name_en: &'static str,
+}
+
+/// Жанр FB2 и группа, к которой он относится
+pub(crate) struct GenreInfo {
+    pub code: &'static str,
+    pub group: &'static str,
+    pub name_ru: &'static str,
+    pub name_en: &'static str,
+}
+
+/// Группа для кодов, которых нет в справочнике
+pub(crate) const OTHER_GROUP: &str = "other";
+
+#[rustfmt::skip]
+pub(crate) static GENRE_GROUPS: &[GenreGroupInfo] = &[
+    group("sf", "Фантастика", "Science Fiction & Fantasy"),
+    group("detective", "Детективы и триллеры", "Detectives & Thrillers"),
+    group("prose", "Проза", "Prose"),
+    group("love", "Любовные романы", "Romance"),
+    group("adventure", "Приключения", "Adventure"),
+    group("children", "Детское", "Children's"),
+    group("poetry", "Поэзия и драматургия", "Poetry & Dramaturgy"),
+    group("antique", "Старинное", "Antique Literature"),
+    group("science", "Наука, образование", "Science & Education"),
+    group("computers", "Компьютеры и интернет", "Computers & Internet"),
+    group("reference", "Справочная литература", "Reference"),
+    group("nonfiction", "Документальная литература", "Nonfiction"),
+    group("religion", "Религия и духовность", "Religion & Spirituality"),
+    group("humor", "Юмор", "Humor"),
+    group("home", "Дом и семья", "Home & Family"),
+    group("business", "Экономика и бизнес", "Business & Economics"),
+    group("military", "Военное дело", "Military"),
+    group("folklore", "Фольклор", "Folklore"),
+    group(OTHER_GROUP, "Прочее", "Other"),
+];
+
+#[rustfmt::skip]
+pub(crate) static GENRES: &[GenreInfo] = &[
+    // Фантастика
+    genre("sf_history", "sf", "Альтернативная история", "Alternative History"),
+    genre("sf_action", "sf", "Боевая фантастика", "Action SF"),
+    genre("sf_epic", "sf", "Эпическая фантастика", "Epic SF"),
+    genre("sf_heroic", "sf", "Героическая фантастика", "Heroic Fantasy"),
+    genre("sf_detective", "sf", "Детективная фантастика", "Detective SF"),
+    genre("sf_cyberpunk", "sf", "Киберпанк", "Cyberpunk"),
+    genre("sf_space", "sf", "Космическая фантастика", "Space SF"),
+    genre("sf_social", "sf", "Социально-психологическая фантастика", "Social SF"),
+    genre("sf_horror", "sf", "Ужасы и мистика", "Horror & Mystic"),
+    genre("sf_humor", "sf", "Юмористическая фантастика", "Humor SF"),
+    genre("sf_fantasy", "sf", "Фэнтези", "Fantasy"),
+    genre("sf_fantasy_city", "sf", "Городское фэнтези", "Urban Fantasy"),
+    genre("sf_mystic", "sf", "Мистика", "Mystic"),
+    genre("sf_postapocalyptic", "sf", "Постапокалипсис", "Postapocalyptic"),
+    genre("sf_stimpank", "sf", "Стимпанк", "Steampunk"),
+    genre("sf_technofantasy", "sf", "Технофэнтези", "Technofantasy"),
+    genre("sf_etc", "sf", "Фантастика: прочее", "Other SF"),
+    genre("sf", "sf", "Научная фантастика", "Science Fiction"),
+    genre("popadanec", "sf", "Попаданцы", "Time Travelers"),
+    genre("litrpg", "sf", "ЛитРПГ", "LitRPG"),
+    genre("hronoopera", "sf", "Хроноопера", "Chrono Opera"),
+    genre("fairy_fantasy", "sf", "Мифологическое фэнтези", "Mythological Fantasy"),
+    // Детективы и триллеры
+    genre("det_classic", "detective", "Классический детектив", "Classical Detective"),
+    genre("det_police", "detective", "Полицейский детектив", "Police Stories"),
+    genre("det_action", "detective", "Боевик", "Action"),
+    genre("det_irony", "detective", "Иронический детектив", "Ironical Detective"),
+    genre("det_history", "detective", "Исторический детектив", "Historical Detective"),
+    genre("det_espionage", "detective", "Шпионский детектив", "Espionage Detective"),
+    genre("det_crime", "detective", "Криминальный детектив", "Crime Detective"),
+    genre("det_political", "detective", "Политический детектив", "Political Detective"),
+    genre("det_maniac", "detective", "Маньяки", "Maniacs"),
+    genre("det_hard", "detective", "Крутой детектив", "Hard-boiled Detective"),
+    genre("thriller", "detective", "Триллер", "Thriller"),
+    genre("detective", "detective", "Детектив", "Detective"),
+    genre("det_su", "detective", "Советский детектив", "Soviet Detective"),
+    // Проза
+    genre("prose_classic", "prose", "Классическая проза", "Classics Prose"),
+    genre("prose_history", "prose", "Историческая проза", "Historical Prose"),
+    genre("prose_contemporary", "prose", "Современная проза", "Contemporary Prose"),
+    genre("prose_counter", "prose", "Контркультура", "Counterculture"),
+    genre("prose_rus_classic", "prose", "Русская классическая проза", "Russian Classics"),
+    genre("prose_su_classics", "prose", "Советская классическая проза", "Soviet Classics"),
+    genre("prose_military", "prose", "Проза о войне", "Military Prose"),
+    genre("prose_abs", "prose", "Фантасмагория, абсурдистская проза", "Absurdist Prose"),
+    genre("prose_neformatny", "prose", "Неформатная проза", "Experimental Prose"),
+    genre("prose_epic", "prose", "Эпопея", "Epic Prose"),
+    genre("prose_magic", "prose", "Магический реализм", "Magic Realism"),
+    genre("aphorisms", "prose", "Афоризмы", "Aphorisms"),
+    genre("essay", "prose", "Эссе, очерк, этюд, набросок", "Essay"),
+    genre("story", "prose", "Малые литературные формы прозы", "Short Story"),
+    genre("great_story", "prose", "Повесть", "Novella"),
+    genre("roman", "prose", "Роман", "Novel"),
+    genre("gothic_novel", "prose", "Готический роман", "Gothic Novel"),
+    genre("epistolary_fiction", "prose", "Эпистолярная проза", "Epistolary Fiction"),
+    genre("sagas", "prose", "Семейный роман/Семейная сага", "Sagas"),
+    genre("short_story", "prose", "Рассказ", "Short Story"),
+    genre("prose", "prose", "Проза", "Prose"),
+    // Любовные романы
+    genre("love_contemporary", "love", "Современные любовные романы", "Contemporary Romance"),
+    genre("love_history", "love", "Исторические любовные романы", "Historical Romance"),
+    genre("love_detective", "love", "Остросюжетные любовные романы", "Detective Romance"),
+    genre("love_short", "love", "Короткие любовные романы", "Short Romance"),
+    genre("love_erotica", "love", "Эротика", "Erotica"),
+    genre("love_sf", "love", "Любовное фэнтези, любовно-фантастические романы", "Romantic Fantasy"),
+    genre("love_hard", "love", "Порно", "Adult"),
+    genre("love", "love", "Любовные романы", "Romance"),
+    // Приключения
+    genre("adv_western", "adventure", "Вестерн", "Western"),
+    genre("adv_history", "adventure", "Исторические приключения", "History Adventure"),
+    genre("adv_indian", "adventure", "Приключения про индейцев", "Indians"),
+    genre("adv_maritime", "adventure", "Морские приключения", "Maritime Fiction"),
+    genre("adv_geo", "adventure", "Путешествия и география", "Travel & Geography"),
+    genre("adv_animal", "adventure", "Природа и животные", "Nature & Animals"),
+    genre("adv_story", "adventure", "Авантюрный роман", "Adventure Story"),
+    genre("adventure", "adventure", "Приключения: прочее", "Other Adventure"),
+    // Детское
+    genre("child_tale", "children", "Сказка", "Fairy Tales"),
+    genre("child_verse", "children", "Детские стихи", "Verses for Children"),
+    genre("child_prose", "children", "Детская проза", "Children's Prose"),
+    genre("child_sf", "children", "Детская фантастика", "Children's SF"),
+    genre("child_det", "children", "Детские остросюжетные", "Children's Action"),
+    genre("child_adv", "children", "Детские приключения", "Children's Adventure"),
+    genre("child_education", "children", "Детская образовательная литература", "Children's Education"),
+    genre("child_folklore", "children", "Детский фольклор", "Children's Folklore"),
+    genre("prose_game", "children", "Игры, упражнения для детей", "Games for Children"),
+    genre("ya", "children", "Подростковая литература", "Young Adult"),
+    genre("children", "children", "Детская литература: прочее", "Other Children's"),
+    // Поэзия и драматургия
+    genre("poetry", "poetry", "Поэзия", "Poetry"),
+    genre("poetry_classical", "poetry", "Классическая поэзия", "Classical Poetry"),
+    genre("poetry_modern", "poetry", "Современная поэзия", "Modern Poetry"),
+    genre("poetry_for_classical", "poetry", "Классическая зарубежная поэзия", "Foreign Classical Poetry"),
+    genre("poetry_for_modern", "poetry", "Современная зарубежная поэзия", "Foreign Modern Poetry"),
+    genre("poetry_rus_classical", "poetry", "Классическая русская поэзия", "Russian Classical Poetry"),
+    genre("poetry_rus_modern", "poetry", "Современная русская поэзия", "Russian Modern Poetry"),
+    genre("poetry_east", "poetry", "Поэзия Востока", "Eastern Poetry"),
+    genre("humor_verse", "poetry", "Юмористические стихи", "Humorous Verse"),
+    genre("lyrics", "poetry", "Лирика", "Lyrics"),
+    genre("song_poetry", "poetry", "Песенная поэзия", "Song Poetry"),
+    genre("experimental_poetry", "poetry", "Экспериментальная поэзия", "Experimental Poetry"),
+    genre("palindromes", "poetry", "Визуальная и экспериментальная поэзия", "Visual Poetry"),
+    genre("dramaturgy", "poetry", "Драматургия", "Dramaturgy"),
+    genre("drama", "poetry", "Драма", "Drama"),
+    genre("comedy", "poetry", "Комедия", "Comedy"),
+    genre("tragedy", "poetry", "Трагедия", "Tragedy"),
+    genre("screenplays", "poetry", "Сценарий", "Screenplays"),
+    genre("scenarios", "poetry", "Сценарии", "Scenarios"),
+    genre("vaudeville", "poetry", "Водевиль", "Vaudeville"),
+    genre("mystery", "poetry", "Мистерия", "Mystery Play"),
+    // Старинное
+    genre("antique_ant", "antique", "Античная литература", "Antique"),
+    genre("antique_european", "antique", "Европейская старинная литература", "European Antique"),
+    genre("antique_russian", "antique", "Древнерусская литература", "Old Russian"),
+    genre("antique_east", "antique", "Древневосточная литература", "Old East"),
+    genre("antique_myths", "antique", "Мифы. Легенды. Эпос", "Myths & Legends"),
+    genre("antique", "antique", "Старинная литература: прочее", "Other Antique"),
+    // Наука, образование
+    genre("sci_history", "science", "История", "History"),
+    genre("sci_psychology", "science", "Психология", "Psychology"),
+    genre("sci_culture", "science", "Культурология", "Cultural Science"),
+    genre("sci_religion", "science", "Религиоведение", "Religious Studies"),
+    genre("sci_philosophy", "science", "Философия", "Philosophy"),
+    genre("sci_politics", "science", "Политика", "Politics"),
+    genre("sci_business", "science", "Деловая литература", "Business Literature"),
+    genre("sci_juris", "science", "Юриспруденция", "Jurisprudence"),
+    genre("sci_linguistic", "science", "Языкознание", "Linguistics"),
+    genre("sci_medicine", "science", "Медицина", "Medicine"),
+    genre("sci_phys", "science", "Физика", "Physics"),
+    genre("sci_math", "science", "Математика", "Mathematics"),
+    genre("sci_chem", "science", "Химия", "Chemistry"),
+    genre("sci_biology", "science", "Биология", "Biology"),
+    genre("sci_tech", "science", "Технические науки", "Technical Sciences"),
+    genre("sci_geo", "science", "Геология и география", "Geology & Geography"),
+    genre("sci_economy", "science", "Экономика", "Economy"),
+    genre("sci_social_studies", "science", "Обществознание, социология", "Social Studies"),
+    genre("sci_pedagogy", "science", "Педагогика", "Pedagogy"),
+    genre("sci_cosmos", "science", "Астрономия и космос", "Astronomy & Space"),
+    genre("sci_ecology", "science", "Экология", "Ecology"),
+    genre("sci_state", "science", "Государство и право", "State & Law"),
+    genre("sci_textbook", "science", "Учебники и пособия", "Textbooks"),
+    genre("sci_popular", "science", "Научно-популярная литература", "Popular Science"),
+    genre("sci_abstract", "science", "Рефераты", "Abstracts"),
+    genre("sci_crib", "science", "Шпаргалки", "Cribs"),
+    genre("sci_veterinary", "science", "Ветеринария", "Veterinary"),
+    genre("sci_zoo", "science", "Зоология", "Zoology"),
+    genre("sci_botany", "science", "Ботаника", "Botany"),
+    genre("sci_orient", "science", "Востоковедение", "Oriental Studies"),
+    genre("science", "science", "Научная литература: прочее", "Other Science"),
+    // Компьютеры и интернет
+    genre("comp_www", "computers", "Интернет", "Internet"),
+    genre("comp_programming", "computers", "Программирование", "Programming"),
+    genre("comp_hard", "computers", "Компьютерное железо", "Hardware"),
+    genre("comp_soft", "computers", "Программы", "Software"),
+    genre("comp_db", "computers", "Базы данных", "Databases"),
+    genre("comp_osnet", "computers", "ОС и сети", "OS & Networking"),
+    genre("comp_dsp", "computers", "Цифровая обработка сигналов", "Digital Signal Processing"),
+    genre("computers", "computers", "Компьютеры: прочее", "Other Computers"),
+    // Справочная литература
+    genre("ref_encyc", "reference", "Энциклопедии", "Encyclopedias"),
+    genre("ref_dict", "reference", "Словари", "Dictionaries"),
+    genre("ref_ref", "reference", "Справочники", "Reference"),
+    genre("ref_guide", "reference", "Руководства", "Guidebooks"),
+    genre("reference", "reference", "Справочная литература: прочее", "Other Reference"),
+    // Документальная литература
+    genre("nonf_biography", "nonfiction", "Биографии и мемуары", "Biography & Memoirs"),
+    genre("nonf_publicism", "nonfiction", "Публицистика", "Publicism"),
+    genre("nonf_criticism", "nonfiction", "Критика", "Criticism"),
+    genre("nonf_military", "nonfiction", "Военная документалистика", "Military Documentary"),
+    genre("design", "nonfiction", "Искусство и дизайн", "Art & Design"),
+    genre("music", "nonfiction", "Музыка", "Music"),
+    genre("cine", "nonfiction", "Кино", "Cinema"),
+    genre("theatre", "nonfiction", "Театр", "Theatre"),
+    genre("architecture_book", "nonfiction", "Архитектура", "Architecture"),
+    genre("visual_arts", "nonfiction", "Изобразительное искусство, фотография", "Visual Arts"),
+    genre("travel_notes", "nonfiction", "Путевые очерки", "Travel Notes"),
+    genre("nonfiction", "nonfiction", "Документальная литература: прочее", "Other Nonfiction"),
+    // Религия и духовность
+    genre("religion_rel", "religion", "Религия", "Religion"),
+    genre("religion_esoterics", "religion", "Эзотерика", "Esoterics"),
+    genre("religion_self", "religion", "Самосовершенствование", "Self-improvement"),
+    genre("religion_orthodoxy", "religion", "Православие", "Orthodoxy"),
+    genre("religion_catholicism", "religion", "Католицизм", "Catholicism"),
+    genre("religion_protestantism", "religion", "Протестантизм", "Protestantism"),
+    genre("religion_islam", "religion", "Ислам", "Islam"),
+    genre("religion_judaism", "religion", "Иудаизм", "Judaism"),
+    genre("religion_budda", "religion", "Буддизм", "Buddhism"),
+    genre("religion_hinduism", "religion", "Индуизм", "Hinduism"),
+    genre("religion_christianity", "religion", "Христианство", "Christianity"),
+    genre("religion_paganism", "religion", "Язычество", "Paganism"),
+    genre("astrology", "religion", "Астрология", "Astrology"),
+    genre("palmistry", "religion", "Хиромантия", "Palmistry"),
+    genre("religion", "religion", "Религия и духовность: прочее", "Other Religion"),
+    // Юмор
+    genre("humor_anecdote", "humor", "Анекдоты", "Anecdotes"),
+    genre("humor_prose", "humor", "Юмористическая проза", "Humorous Prose"),
+    genre("humor_satire", "humor", "Сатира", "Satire"),
+    genre("humor_fantasy", "humor", "Юмористическое фэнтези", "Humorous Fantasy"),
+    genre("humor", "humor", "Юмор: прочее", "Other Humor"),
+    // Дом и семья
+    genre("home_cooking", "home", "Кулинария", "Cooking"),
+    genre("home_pets", "home", "Домашние животные", "Pets"),
+    genre("home_crafts", "home", "Хобби и ремёсла", "Hobbies & Crafts"),
+    genre("home_entertain", "home", "Развлечения", "Entertaining"),
+    genre("home_health", "home", "Здоровье", "Health"),
+    genre("home_garden", "home", "Сад и огород", "Garden"),
+    genre("home_diy", "home", "Сделай сам", "Do It Yourself"),
+    genre("home_sport", "home", "Спорт", "Sports"),
+    genre("home_sex", "home", "Эротика, секс", "Erotica & Sex"),
+    genre("home_collecting", "home", "Коллекционирование", "Collecting"),
+    genre("family", "home", "Семейные отношения", "Family"),
+    genre("auto_regulations", "home", "Автомобили и ПДД", "Cars & Traffic Rules"),
+    genre("home", "home", "Домоводство", "Home"),
+    // Экономика и бизнес
+    genre("banking", "business", "Банковское дело", "Banking"),
+    genre("accounting", "business", "Бухучёт и аудит", "Accounting"),
+    genre("global_economy", "business", "Внешнеэкономическая деятельность", "Global Economy"),
+    genre("paper_work", "business", "Делопроизводство", "Paper Work"),
+    genre("org_behavior", "business", "Корпоративная культура", "Corporate Culture"),
+    genre("personal_finance", "business", "Личные финансы", "Personal Finance"),
+    genre("small_business", "business", "Малый бизнес", "Small Business"),
+    genre("marketing", "business", "Маркетинг, PR, реклама", "Marketing, PR & Advertising"),
+    genre("real_estate", "business", "Недвижимость", "Real Estate"),
+    genre("popular_business", "business", "Популярно о бизнесе", "Popular Business"),
+    genre("industries", "business", "Отраслевые издания", "Industries"),
+    genre("job_hunting", "business", "Поиск работы, карьера", "Job Hunting & Career"),
+    genre("management", "business", "Управление, подбор персонала", "Management"),
+    genre("stock", "business", "Ценные бумаги, инвестиции", "Stocks & Investments"),
+    genre("economics", "business", "Экономика", "Economics"),
+    genre("economics_ref", "business", "Деловая литература", "Business Reference"),
+    // Военное дело
+    genre("military_weapon", "military", "Военная техника и вооружение", "Weapons"),
+    genre("military_history", "military", "Военная история", "Military History"),
+    genre("military_special", "military", "Военное дело: прочее", "Military Special"),
+    genre("military_arts", "military", "Боевые искусства", "Martial Arts"),
+    genre("military", "military", "Военное дело", "Military"),
+    // Фольклор
+    genre("folklore", "folklore", "Фольклор: прочее", "Folklore"),
+    genre("folk_tale", "folklore", "Народные сказки", "Folk Tales"),
+    genre("folk_songs", "folklore", "Народные песни", "Folk Songs"),
+    genre("proverbs", "folklore", "Пословицы, поговорки", "Proverbs"),
+    genre("epic", "folklore", "Былины", "Epic"),
+    genre("riddles", "folklore", "Загадки", "Riddles"),
+    genre("limerick", "folklore", "Частушки, прибаутки, потешки", "Limericks"),
+    genre("antique_folk", "folklore", "Старинный фольклор", "Old Folklore"),
+    // Прочее
+    genre("other", OTHER_GROUP, "Неотсортированное", "Unsorted"),
+    genre("periodic", OTHER_GROUP, "Журналы, газеты", "Periodicals"),
+    genre("comics", OTHER_GROUP, "Комиксы", "Comics"),
+    genre("notes", OTHER_GROUP, "Партитуры", "Notes"),
+    genre("fanfiction", OTHER_GROUP, "Фанфик", "Fan Fiction"),
+    genre("unfinished", OTHER_GROUP, "Недописанное", "Unfinished"),
+];
+
+const fn group(code: &'static str, name_ru: &'static str, name_en: &'static str) -> GenreGroupInfo {
+    GenreGroupInfo {
+        code,
+        name_ru,
+        name_en,
+    }
+}
+
+const fn genre(
+    code: &'static str,
+    group: &'static str,
+    name_ru: &'static str,
+    name_en: &'static str,
+) -> GenreInfo {
+    GenreInfo {
+        code,
+        group,
+        name_ru,
+        name_en,
+    }
+}
+
+/// Поиск жанра по коду
+pub(crate) fn find_genre(code: &str) -> Option<&'static GenreInfo> {
+    GENRES.iter().find(|g| g.code == code)
+}
+
+/// Группа, к которой относится код жанра; неизвестные коды попадают в `other`
+pub(crate) fn genre_group(code: &str) -> &'static str {
+    find_genre(code).map(|g| g.group).unwrap_or(OTHER_GROUP)
+}
+
+/// Фасет жанра в индексе: `/группа/код`
+pub(crate) fn genre_facet(code: &str) -> Facet {
+    Facet::from_path([genre_group(code), code])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn genre_codes_are_unique() {
+        let mut seen = HashSet::new();
+        for genre in GENRES {
+            assert!(seen.insert(genre.code), "повторный код {}", genre.code);
+        }
+        let groups: HashSet<&str> = GENRE_GROUPS.iter().map(|g| g.code).collect();
+        assert_eq!(groups.len(), GENRE_GROUPS.len());
+    }
+
+    #[test]
+    fn every_genre_belongs_to_a_known_group() {
+        for genre in GENRES {
+            assert!(
+                GENRE_GROUPS.iter().any(|g| g.code == genre.group),
+                "жанр {} в неизвестной группе {}",
+                genre.code,
+                genre.group
+            );
+        }
+        assert_eq!(
+            genre_facet("sf_fantasy"),
+            Facet::from_path(["sf", "sf_fantasy"])
+        );
+        assert_eq!(
+            genre_facet("no_such"),
+            Facet::from_path([OTHER_GROUP, "no_such"])
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Подсветка совпавших слов в авторе и названии найденной книги.
+//! Термы берутся из итогового запроса, слова текста разбираются тем же анализатором,
+//! что и при индексации, поэтому «войны» подсвечивает «Война». Нечёткие термы
+//! раскрываются в слова словаря, а совпадения по латинским написаниям переносятся
+//! на исходные слова
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use tantivy::query::{BooleanQuery, Occur, Query, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::tokenizer::{TextAnalyzer, Token};
+use tantivy::{Searcher, SnippetGenerator, TantivyError, Term};
+
+use crate::{analyzer, fuzzy_distance, spelling, translit, Book, BookFields, Snippet};
+
+/// Подсветка одного поля: термы самого поля и его латинских написаний
+struct FieldHighlighter {
+    generator: SnippetGenerator,
+    translit_terms: HashSet<String>,
+}
+
+/// Генераторы подсветки для одной страницы результатов
+pub(crate) struct Highlighter {
+    author: FieldHighlighter,
+    title: FieldHighlighter,
+    translit_analyzer: TextAnalyzer,
+    markers: (String, String),
+    escape_html: bool, // Маркеры - теги HTML, текст между ними экранируется
+}
+
+/// Маркер похож на тег HTML (`<b>`, `<mark class="hit">`)
+fn is_html_tag(marker: &str) -> bool {
+    marker.starts_with('<') && marker.ends_with('>')
+}
+
+/// Добавление текста с экранированием спецсимволов HTML
+fn push_escaped(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#x27;"),
+            c => out.push(c),
+        }
+    }
+}
+
+impl Highlighter {
+    /// `fuzzy` - запрос искал с опечатками, его термы подсвечивают и похожие слова
+    pub(crate) fn new(
+        searcher: &Searcher,
+        query: &dyn Query,
+        fields: &BookFields,
+        fuzzy: bool,
+        markers: (String, String),
+    ) -> Result<Self, TantivyError> {
+        let field_terms = |field: Field| -> Result<BTreeSet<String>, TantivyError> {
+            let mut terms = BTreeSet::new();
+            query.query_terms(&mut |term, _| {
+                if term.field() == field {
+                    terms.extend(term.as_str().map(str::to_string));
+                }
+            });
+            if fuzzy {
+                // Те же расстояния, что и в нечётком запросе
+                for text in terms.clone() {
+                    let distance = fuzzy_distance(&text);
+                    if distance > 0 {
+                        terms.extend(
+                            spelling::similar_terms(searcher, field, &text, distance)?.into_keys(),
+                        );
+                    }
+                }
+            }
+            Ok(terms)
+        };
+        let highlighter = |field, translit_field| -> Result<FieldHighlighter, TantivyError> {
+            let terms: Vec<(Occur, Box<dyn Query>)> = field_terms(field)?
+                .into_iter()
+                .map(|text| {
+                    let term = Term::from_field_text(field, &text);
+                    let query: Box<dyn Query> =
+                        Box::new(TermQuery::new(term, IndexRecordOption::Basic));
+                    (Occur::Should, query)
+                })
+                .collect();
+            let mut generator =
+                SnippetGenerator::create(searcher, &BooleanQuery::new(terms), field)?;
+            // Авторы и названия короткие: подсвечиваем текст целиком, без обрезки
+            generator.set_max_num_chars(usize::MAX);
+            Ok(FieldHighlighter {
+                generator,
+                translit_terms: field_terms(translit_field)?.into_iter().collect(),
+            })
+        };
+        Ok(Highlighter {
+            author: highlighter(fields.author, fields.author_translit)?,
+            title: highlighter(fields.title, fields.title_translit)?,
+            translit_analyzer: searcher
+                .index()
+                .tokenizer_for_field(fields.author_translit)?,
+            escape_html: is_html_tag(&markers.0) && is_html_tag(&markers.1),
+            markers,
+        })
+    }
+
+    /// Подсветка полей `author` (авторы через запятую) и `title`
+    pub(crate) fn snippets(&self, book: &Book) -> HashMap<String, Snippet> {
+        let authors = book
+            .authors
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        HashMap::from([
+            ("author".to_string(), self.snippet(&self.author, &authors)),
+            (
+                "title".to_string(),
+                self.snippet(&self.title, &book.book_title),
+            ),
+        ])
+    }
+
+    fn snippet(&self, field: &FieldHighlighter, text: &str) -> Snippet {
+        // Фрагмент без ограничения длины начинается с начала текста,
+        // поэтому его байтовые диапазоны совпадают с диапазонами в `text`
+        let mut ranges: Vec<(usize, usize)> = field
+            .generator
+            .snippet(text)
+            .highlighted()
+            .iter()
+            .map(|range| (range.start, range.end))
+            .collect();
+        ranges.extend(self.translit_ranges(&field.translit_terms, text));
+        ranges.sort_unstable();
+        ranges.dedup_by(|next, prev| {
+            let overlaps = next.0 <= prev.1;
+            if overlaps {
+                prev.1 = prev.1.max(next.1);
+            }
+            overlaps
+        });
+
+        let (open, close) = &self.markers;
+        let mut marked = String::with_capacity(text.len());
+        let push_text = |marked: &mut String, text: &str| {
+            if self.escape_html {
+                push_escaped(marked, text);
+            } else {
+                marked.push_str(text);
+            }
+        };
+        let mut last = 0;
+        for &(start, end) in &ranges {
+            push_text(&mut marked, &text[last..start]);
+            marked.push_str(open);
+            push_text(&mut marked, &text[start..end]);
+            marked.push_str(close);
+            last = end;
+        }
+        push_text(&mut marked, &text[last..]);
+
+        // Для Python - смещения в символах, а не в байтах UTF-8
+        let char_offset = |byte: usize| text[..byte].chars().count();
+        Snippet {
+            text: text.to_string(),
+            ranges: ranges
+                .into_iter()
+                .map(|(start, end)| (char_offset(start), char_offset(end)))
+                .collect(),
+            marked,
+        }
+    }
+
+    /// Слова текста, латинское написание которых совпало с термом запроса
+    fn translit_ranges(&self, terms: &HashSet<String>, text: &str) -> Vec<(usize, usize)> {
+        if terms.is_empty() {
+            return Vec::new();
+        }
+        analyzer::spell_tokens(text)
+            .into_iter()
+            .filter(|word| {
+                translit::romanize_variants(&word.text)
+                    .iter()
+                    .any(|variant| {
+                        let mut matched = false;
+                        self.translit_analyzer
+                            .token_stream(variant)
+                            .process(&mut |token: &Token| matched |= terms.contains(&token.text));
+                        matched
+                    })
+            })
+            .map(|word| (word.offset_from, word.offset_to))
+            .collect()
+    }
+}
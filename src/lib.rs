@@ -1,449 +1,1641 @@
+// `#[pymethods]` в pyo3 0.22 генерирует `.into()` для `PyResult`, на что ругается clippy
+#![allow(clippy::useless_conversion)]
+
+mod analyzer;
+mod catalog;
+mod collation;
+mod facets;
+mod genres;
+mod highlight;
+mod query_syntax;
+mod sorting;
+mod spelling;
+mod suggest;
+#[cfg(test)]
+mod testing;
+mod translit;
+
+use catalog::AuthorCatalog;
+use highlight::Highlighter;
 use partialzip::PartialZip;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::PyBytes;
 use serde::{Deserialize, Serialize};
+use sorting::{SortOrder, SortTweaker};
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
+use std::fmt;
 use std::fs::{self, File};
 use std::io::Read;
-use std::path::{Path, PathBuf};
-use tantivy::collector::TopDocs;
-use tantivy::query::QueryParser;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Instant, SystemTime};
+use suggest::SuggestKind;
+use tantivy::collector::{Count, DocSetCollector, FacetCollector, TopDocs};
+use tantivy::query::{
+    AllQuery, BooleanQuery, BoostQuery, ConstScoreQuery, FuzzyTermQuery, Occur, Query, QueryParser,
+    RangeQuery, TermQuery, TermSetQuery,
+};
 use tantivy::schema::*;
+use tantivy::time::{Date, Month};
+use tantivy::tokenizer::Token;
 use tantivy::Document as TantivyDocument;
-use tantivy::{Index, TantivyError};
+use tantivy::{DateTime, Index, IndexReader, ReloadPolicy, TantivyError};
 use url::Url;
 use zip::ZipArchive;
 
+/// Автор книги, разобранный из записи `Фамилия,Имя,Отчество` колонки AUTHOR
+#[pyclass(get_all)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Author {
+    last_name: String,
+    first_name: String,
+    middle_name: String,
+    name: String, // Отображаемое имя: `Лев Николаевич Толстой`
+    key: String,  // Идентификатор автора: `толстой лев николаевич`, см. `collation::normalize`
+}
+
+impl Author {
+    /// Разбор одной записи `Фамилия,Имя,Отчество`, `None` если запись пустая
+    fn parse(entry: &str) -> Option<Author> {
+        let mut parts = entry.splitn(3, ',').map(str::trim);
+        let last_name = parts.next().unwrap_or("").to_string();
+        let first_name = parts.next().unwrap_or("").to_string();
+        let middle_name = parts.next().unwrap_or("").to_string();
+        let name = [&first_name, &middle_name, &last_name]
+            .iter()
+            .filter(|s| !s.is_empty())
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if name.is_empty() {
+            return None;
+        }
+        let mut author = Author {
+            last_name,
+            first_name,
+            middle_name,
+            name,
+            key: String::new(),
+        };
+        author.key = collation::normalize(&author.sort_name());
+        Some(author)
+    }
+
+    /// Имя в порядке каталога: `Толстой Лев Николаевич`
+    fn sort_name(&self) -> String {
+        [&self.last_name, &self.first_name, &self.middle_name]
+            .iter()
+            .filter(|s| !s.is_empty())
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Разбор колонки AUTHOR: список записей через `:`
+    fn parse_list(column: &str) -> Vec<Author> {
+        column.split(':').filter_map(Author::parse).collect()
+    }
+
+    /// Запись в исходном виде `Фамилия,Имя,Отчество` для хранения в индексе
+    fn raw(&self) -> String {
+        format!(
+            "{},{},{}",
+            self.last_name, self.first_name, self.middle_name
+        )
+    }
+}
+
+#[pymethods]
+impl Author {
+    fn __repr__(&self) -> String {
+        format!("Author('{}')", self.name)
+    }
+}
+
+/// Автор из каталога и число его книг (без удалённых)
+#[pyclass(get_all)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct AuthorCount {
+    author: Author,
+    count: u64,
+}
+
 /// Структура для хранения информации о книге
+#[pyclass(get_all)]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct Book {
     id: u64, // Изменено на u64 для соответствия Tantivy
-    author_name: String,
+    authors: Vec<Author>,
     book_title: String,
-    zip_archive: String, // Относительный путь к zip-архиву
+    genres: Vec<String>, // Коды жанров FB2 (`sf_fantasy`, `det_classic`, ...)
+    series: String,
+    serno: u64,    // Номер книги в серии, 0 если не указан
+    size: u64,     // Размер файла в байтах
+    libid: u64,    // LIBID из библиотеки-источника
+    deleted: bool, // Флаг DEL
+    ext: String,   // Расширение файла (`fb2`, `epub`, ...)
+    date: String,  // Дата добавления в формате `YYYY-MM-DD`
+    lang: String,
+    rating: u64, // LIBRATE, 0 если не указан
+    keywords: String,
+    zip_archive: String, // Имя zip-архива в каталоге `zip_archives_dir`
+}
+
+impl Book {
+    /// Отпечаток всех полей книги (FNV-1a от bincode), по нему инкрементальный
+    /// импорт понимает, изменились ли метаданные. Из пути к архиву берётся только
+    /// имя файла: индексы прежних версий хранили полный путь с `zip_archives_dir`
+    fn fingerprint(&self) -> u64 {
+        let archive_name = Path::new(&self.zip_archive)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let book = Book {
+            zip_archive: archive_name,
+            ..self.clone()
+        };
+        let bytes = bincode::serialize(&book).unwrap_or_default();
+        bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+        })
+    }
+}
+
+/// Результат поиска: книга и её релевантность
+#[pyclass(get_all)]
+#[derive(Debug, Clone)]
+struct SearchHit {
+    book: Book,
+    score: f32, // BM25 score
+    // Подсветка совпадений по полям `author` и `title` (пусто, если не запрошена)
+    snippets: HashMap<String, Snippet>,
+}
+
+/// Поле книги с подсвеченными совпадениями запроса
+#[pyclass(get_all)]
+#[derive(Debug, Clone)]
+struct Snippet {
+    text: String,                // Исходный текст поля
+    ranges: Vec<(usize, usize)>, // Совпадения: смещения `[start, end)` в символах `text`
+    // Текст с маркерами вокруг совпадений; при маркерах-тегах HTML текст экранирован
+    marked: String,
+}
+
+/// Страница результатов поиска
+#[pyclass(get_all)]
+#[derive(Debug, Clone)]
+struct SearchPage {
+    hits: Vec<SearchHit>,
+    total: usize, // Общее число найденных книг
+    offset: usize,
+    limit: usize,
+    // Число найденных книг по `genre`, `genre_group`, `lang`, `ext` и `decade`
+    // (пусто, если фасеты не запрошены)
+    facets: HashMap<String, HashMap<String, u64>>,
+    // Запрос с исправленными опечатками, если по исходному почти ничего не нашлось
+    suggestion: Option<String>,
+}
+
+#[pymethods]
+impl SearchPage {
+    /// Есть ли результаты за пределами этой страницы
+    #[getter]
+    fn has_more(&self) -> bool {
+        self.offset.saturating_add(self.hits.len()) < self.total
+    }
+
+    fn __len__(&self) -> usize {
+        self.hits.len()
+    }
+}
+
+/// Параметры поиска, общие для всех режимов
+#[derive(Debug, Clone)]
+struct SearchOptions {
+    include_deleted: bool,
+    fuzzy: bool,    // Допускать опечатки в словах запроса
+    translit: bool, // Искать также по латинским написаниям
+    strict: bool,   // Ошибка в синтаксисе запроса - исключение, а не поиск по словам
+    facets: bool,   // Считать найденные книги по жанрам, языкам, форматам и десятилетиям
+    filters: SearchFilters,
+    sort: SortOrder,
+    reverse: bool, // Обратное направление сортировки (кроме сортировки по релевантности)
+    highlight: Option<(String, String)>, // Маркеры начала и конца подсветки совпадений
+    limit: usize,
+    offset: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            include_deleted: false,
+            fuzzy: false,
+            translit: true,
+            strict: false,
+            facets: false,
+            filters: SearchFilters::default(),
+            sort: SortOrder::Relevance,
+            reverse: false,
+            highlight: None,
+            limit: 10,
+            offset: 0,
+        }
+    }
+}
+
+/// Фильтры поиска: отбирают книги, не влияя на score. Пустой список или `None` - без фильтра
+#[derive(Debug, Clone, Default)]
+struct SearchFilters {
+    langs: Vec<String>,
+    exts: Vec<String>,
+    date_from: Option<DateTime>, // Включительно
+    date_to: Option<DateTime>,   // Включительно
+    size_min: Option<u64>,
+    size_max: Option<u64>,
+    min_rating: Option<u64>,
+}
+
+/// Код языка, формата или жанра в том виде, в каком он лежит в индексе: `' RU'` -> `'ru'`
+fn facet_code(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+impl SearchFilters {
+    /// Фильтры из аргументов Python; даты - в виде `YYYY-MM-DD`, как в INPX
+    fn parse(
+        langs: Option<Vec<String>>,
+        exts: Option<Vec<String>>,
+        date_from: Option<String>,
+        date_to: Option<String>,
+        size_min: Option<u64>,
+        size_max: Option<u64>,
+        min_rating: Option<u64>,
+    ) -> Result<Self, String> {
+        let date = |name: &str, value: Option<String>| -> Result<Option<DateTime>, String> {
+            value
+                .map(|value| {
+                    parse_inp_date(&value).ok_or_else(|| {
+                        format!("Неверная дата {}='{}', ожидается YYYY-MM-DD", name, value)
+                    })
+                })
+                .transpose()
+        };
+        Ok(SearchFilters {
+            langs: langs.iter().flatten().map(|l| facet_code(l)).collect(),
+            exts: exts.iter().flatten().map(|e| facet_code(e)).collect(),
+            date_from: date("date_from", date_from)?,
+            date_to: date("date_to", date_to)?,
+            size_min,
+            size_max,
+            min_rating,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.langs.is_empty()
+            && self.exts.is_empty()
+            && self.date_from.is_none()
+            && self.date_to.is_none()
+            && self.size_min.is_none()
+            && self.size_max.is_none()
+            && self.min_rating.is_none()
+    }
+}
+
+/// Структурированный запрос: каждое заданное поле должно совпасть,
+/// значения не разбираются как синтаксис запроса
+#[derive(Debug, Clone, Default)]
+struct FieldQuery {
+    author: Option<String>,
+    title: Option<String>,
+    series: Option<String>,
+    genre: Option<String>,       // Код жанра (`sf_fantasy`)
+    genre_group: Option<String>, // Код группы жанров (`sf`)
+    lang: Option<String>,
+}
+
+/// Серия с числом книг и её авторами
+#[pyclass(get_all)]
+#[derive(Debug, Clone)]
+struct Series {
+    name: String,         // Название в том виде, как оно записано в INPX
+    count: u64,           // Число книг серии
+    authors: Vec<String>, // Авторы книг серии без повторов
+}
+
+/// Подсказка при вводе: автор, название или серия
+#[pyclass(get_all)]
+#[derive(Debug, Clone)]
+struct Suggestion {
+    text: String, // Самое частое написание в INPX
+    key: String,  // Нормализованный ключ: для авторов - `author_key` в `books_by_author`
+    count: u64,   // Число книг (с DEL=1 - только при `include_deleted`)
+}
+
+/// Книги одной серии в порядке чтения
+#[pyclass(get_all)]
+#[derive(Debug, Clone)]
+struct SeriesBooks {
+    name: String,
+    books: Vec<Book>,
+}
+
+/// Библиография автора: книги по сериям и отдельные произведения
+#[pyclass(get_all)]
+#[derive(Debug, Clone)]
+struct Bibliography {
+    author: Option<Author>, // `None`, если книг автора в индексе нет
+    series: Vec<SeriesBooks>,
+    standalone: Vec<Book>, // Книги вне серий, по названию
 }
 
-/// Создание схемы для Tantivy с добавленными полями `id`, `zip_archive` и `internal_file_name`
+/// Жанр FB2 с числом книг в индексе
+#[pyclass(get_all)]
+#[derive(Debug, Clone)]
+struct Genre {
+    code: String,
+    name_ru: String,
+    name_en: String,
+    count: u64,
+}
+
+/// Группа жанров верхнего уровня с вложенными жанрами
+#[pyclass(get_all)]
+#[derive(Debug, Clone)]
+struct GenreGroup {
+    code: String,
+    name_ru: String,
+    name_en: String,
+    count: u64,
+    genres: Vec<Genre>,
+}
+
+/// Ошибка: книга помечена в INPX как удалённая (DEL=1)
+#[derive(Debug)]
+struct BookDeleted(u64);
+
+impl fmt::Display for BookDeleted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Книга с ID {} удалена из библиотеки", self.0)
+    }
+}
+
+impl Error for BookDeleted {}
+
+/// Ошибка строгого разбора запроса с местом, где она найдена
+#[derive(Debug)]
+struct QuerySyntax {
+    query: String,
+    position: Option<usize>, // Позиция символа в запросе, если её удалось определить
+    reason: String,
+}
+
+impl fmt::Display for QuerySyntax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some(position) => write!(
+                f,
+                "Ошибка в запросе в позиции {}: {}",
+                position, self.reason
+            ),
+            None => write!(f, "Ошибка в запросе: {}", self.reason),
+        }
+    }
+}
+
+impl Error for QuerySyntax {}
+
+create_exception!(flib_rs, BookDeletedError, PyRuntimeError);
+create_exception!(flib_rs, QuerySyntaxError, PyValueError);
+
+/// Преобразование ошибки в исключение Python, удалённые книги и ошибки
+/// в запросе - отдельными типами
+fn to_py_err<E: Into<Box<dyn Error>>>(e: E) -> PyErr {
+    let e = e.into();
+    if let Some(syntax) = e.downcast_ref::<QuerySyntax>() {
+        let err = QuerySyntaxError::new_err(syntax.to_string());
+        Python::with_gil(|py| {
+            let value = err.value_bound(py);
+            // Атрибуты для программного разбора ошибки; setattr на экземпляре исключения не падает
+            let _ = value.setattr("query", &syntax.query);
+            let _ = value.setattr("position", syntax.position);
+            let _ = value.setattr("reason", &syntax.reason);
+        });
+        err
+    } else if e.is::<BookDeleted>() {
+        BookDeletedError::new_err(e.to_string())
+    } else {
+        PyRuntimeError::new_err(e.to_string())
+    }
+}
+
+/// Фасет ключа книги в поле `field`; для книг без DEL=1 - ещё и в поле `live` для подсказок
+fn add_key_facet(
+    doc: &mut TantivyDocument,
+    field: Field,
+    live: Field,
+    deleted: bool,
+    facet: Facet,
+) {
+    if !deleted {
+        doc.add_facet(live, facet.clone());
+    }
+    doc.add_facet(field, facet);
+}
+
+/// Хэндлы полей схемы, чтобы не искать их по имени в каждой функции
+#[derive(Clone, Copy)]
+struct BookFields {
+    id: Field,
+    author: Field,
+    author_raw: Field,
+    author_translit: Field,
+    author_key: Field,
+    author_key_live: Field,
+    title: Field,
+    title_translit: Field,
+    title_key: Field,
+    title_key_live: Field,
+    spell: Field,
+    title_sort: Field,
+    author_sort: Field,
+    zip_archive: Field,
+    genre: Field,
+    series: Field,
+    series_key: Field,
+    series_key_live: Field,
+    serno: Field,
+    size: Field,
+    libid: Field,
+    deleted: Field,
+    ext: Field,
+    date: Field,
+    lang: Field,
+    rating: Field,
+    keywords: Field,
+    fingerprint: Field,
+}
+
+impl BookFields {
+    fn from_schema(schema: &Schema) -> Result<Self, TantivyError> {
+        let field = |name: &str| {
+            schema.get_field(name).ok_or_else(|| {
+                TantivyError::SchemaError(format!(
+                    "Поле '{}' не найдено в схеме индекса, пересоздайте индекс",
+                    name
+                ))
+            })
+        };
+        Ok(BookFields {
+            id: field("id")?,
+            author: field("author")?,
+            author_raw: field("author_raw")?,
+            author_translit: field("author_translit")?,
+            author_key: field("author_key")?,
+            author_key_live: field("author_key_live")?,
+            title: field("title")?,
+            title_translit: field("title_translit")?,
+            title_key: field("title_key")?,
+            title_key_live: field("title_key_live")?,
+            spell: field("spell")?,
+            title_sort: field("title_sort")?,
+            author_sort: field("author_sort")?,
+            zip_archive: field("zip_archive")?,
+            genre: field("genre")?,
+            series: field("series")?,
+            series_key: field("series_key")?,
+            series_key_live: field("series_key_live")?,
+            serno: field("serno")?,
+            size: field("size")?,
+            libid: field("libid")?,
+            deleted: field("deleted")?,
+            ext: field("ext")?,
+            date: field("date")?,
+            lang: field("lang")?,
+            rating: field("rating")?,
+            keywords: field("keywords")?,
+            fingerprint: field("fingerprint")?,
+        })
+    }
+
+    /// Преобразование книги в документ Tantivy
+    fn book_document(&self, book: &Book) -> TantivyDocument {
+        let mut doc = TantivyDocument::new();
+        doc.add_u64(self.id, book.id);
+        // Каждый автор индексируется отдельным значением поля
+        for author in &book.authors {
+            doc.add_text(self.author, &author.name);
+            doc.add_text(self.spell, &author.name);
+            doc.add_text(self.author_raw, author.raw());
+            for variant in translit::romanize_variants(&author.name) {
+                doc.add_text(self.author_translit, variant);
+            }
+            // Второй уровень фасета - написание имени для подсказок (см. `suggest`)
+            add_key_facet(
+                &mut doc,
+                self.author_key,
+                self.author_key_live,
+                book.deleted,
+                Facet::from_path([author.key.as_str(), author.name.as_str()]),
+            );
+        }
+        doc.add_text(self.title, &book.book_title);
+        doc.add_text(self.spell, &book.book_title);
+        let title_key = collation::normalize(&book.book_title);
+        if !title_key.is_empty() {
+            add_key_facet(
+                &mut doc,
+                self.title_key,
+                self.title_key_live,
+                book.deleted,
+                Facet::from_path([title_key.as_str(), book.book_title.as_str()]),
+            );
+        }
+        for variant in translit::romanize_variants(&book.book_title) {
+            doc.add_text(self.title_translit, variant);
+        }
+        doc.add_u64(self.title_sort, collation::sort_key(&book.book_title));
+        let first_author = book.authors.first().map(Author::sort_name);
+        doc.add_u64(
+            self.author_sort,
+            collation::sort_key(first_author.as_deref().unwrap_or("")),
+        );
+        doc.add_text(self.zip_archive, &book.zip_archive);
+        for genre in &book.genres {
+            doc.add_facet(self.genre, genres::genre_facet(genre));
+        }
+        doc.add_text(self.series, &book.series);
+        let series_key = collation::normalize(&book.series);
+        if !series_key.is_empty() {
+            add_key_facet(
+                &mut doc,
+                self.series_key,
+                self.series_key_live,
+                book.deleted,
+                Facet::from_path([series_key.as_str(), book.series.as_str()]),
+            );
+        }
+        doc.add_u64(self.serno, book.serno);
+        doc.add_u64(self.size, book.size);
+        doc.add_u64(self.libid, book.libid);
+        doc.add_bool(self.deleted, book.deleted);
+        if !book.ext.is_empty() {
+            doc.add_facet(self.ext, Facet::from_path([book.ext.as_str()]));
+        }
+        if let Some(date) = parse_inp_date(&book.date) {
+            doc.add_date(self.date, date);
+        }
+        if !book.lang.is_empty() {
+            doc.add_facet(self.lang, Facet::from_path([book.lang.as_str()]));
+        }
+        doc.add_u64(self.rating, book.rating);
+        doc.add_text(self.keywords, &book.keywords);
+        doc.add_u64(self.fingerprint, book.fingerprint());
+        doc
+    }
+
+    /// Восстановление книги из сохранённого документа Tantivy
+    fn read_book(&self, doc: &TantivyDocument) -> Book {
+        let text = |field: Field| {
+            doc.get_first(field)
+                .and_then(|v| v.as_text())
+                .unwrap_or("")
+                .to_string()
+        };
+        let number = |field: Field| doc.get_first(field).and_then(|v| v.as_u64()).unwrap_or(0);
+        let facets = |field: Field| -> Vec<String> {
+            doc.get_all(field)
+                .filter_map(|v| v.as_facet())
+                .filter_map(|f| f.to_path().last().map(|s| s.to_string()))
+                .collect()
+        };
+        Book {
+            id: number(self.id),
+            authors: doc
+                .get_all(self.author_raw)
+                .filter_map(|v| v.as_text())
+                .filter_map(Author::parse)
+                .collect(),
+            book_title: text(self.title),
+            genres: facets(self.genre),
+            series: text(self.series),
+            serno: number(self.serno),
+            size: number(self.size),
+            libid: number(self.libid),
+            deleted: doc
+                .get_first(self.deleted)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            ext: facets(self.ext).pop().unwrap_or_default(),
+            date: doc
+                .get_first(self.date)
+                .and_then(|v| v.as_date())
+                .map(format_inp_date)
+                .unwrap_or_default(),
+            lang: facets(self.lang).pop().unwrap_or_default(),
+            rating: number(self.rating),
+            keywords: text(self.keywords),
+            zip_archive: text(self.zip_archive),
+        }
+    }
+}
+
+/// Создание схемы для Tantivy со всеми колонками INP
 fn create_schema() -> Schema {
     let mut schema_builder = Schema::builder();
     schema_builder.add_u64_field("id", INDEXED | STORED | FAST); // Поле `id`
-    schema_builder.add_text_field("author", TEXT | FAST | STORED);
-    schema_builder.add_text_field("title", TEXT | FAST | STORED);
+
+    // Автор и название разбираются русским анализатором со стеммингом
+    let ru_text = TextOptions::default()
+        .set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(analyzer::RU_STEM)
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        )
+        .set_stored();
+    schema_builder.add_text_field("author", ru_text.clone()); // `Лев Николаевич Толстой`
+    schema_builder.add_text_field("author_raw", STORED); // `Толстой,Лев,Николаевич`
+    schema_builder.add_facet_field("author_key", INDEXED); // `/толстой лев николаевич`
+    schema_builder.add_text_field("title", ru_text.clone());
+    // `/мастер и маргарита/Мастер и Маргарита`: второй уровень - написание для подсказок
+    schema_builder.add_facet_field("title_key", INDEXED);
+    // Слова автора и названия без стемминга: словарь для исправления опечаток
+    let spell_text = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(analyzer::SPELL)
+            .set_index_option(IndexRecordOption::Basic),
+    );
+    schema_builder.add_text_field("spell", spell_text);
+    // Латинские написания автора и названия для поиска между алфавитами
+    schema_builder.add_text_field("author_translit", TEXT);
+    schema_builder.add_text_field("title_translit", TEXT);
+    // Ключи сортировки по названию и первому автору (см. `collation`)
+    schema_builder.add_u64_field("title_sort", FAST);
+    schema_builder.add_u64_field("author_sort", FAST);
     schema_builder.add_text_field("zip_archive", TEXT | STORED); // Поле `zip_archive`
+    schema_builder.add_facet_field("genre", INDEXED | STORED); // `/sf/sf_fantasy`
+    schema_builder.add_text_field("series", ru_text); // Со стеммингом: «дозор» находит «Дозоры»
+    schema_builder.add_facet_field("series_key", INDEXED); // `/дозоры`, см. `collation::normalize`
+                                                           // Те же ключи только для книг без DEL=1: подсказки берут число книг прямо из словаря
+    schema_builder.add_facet_field("author_key_live", INDEXED);
+    schema_builder.add_facet_field("title_key_live", INDEXED);
+    schema_builder.add_facet_field("series_key_live", INDEXED);
+    schema_builder.add_u64_field("serno", INDEXED | STORED | FAST);
+    schema_builder.add_u64_field("size", INDEXED | STORED | FAST);
+    schema_builder.add_u64_field("libid", INDEXED | STORED | FAST);
+    schema_builder.add_bool_field("deleted", INDEXED | STORED | FAST);
+    schema_builder.add_facet_field("ext", INDEXED | STORED); // `/fb2`
+    schema_builder.add_date_field("date", INDEXED | STORED | FAST);
+    schema_builder.add_facet_field("lang", INDEXED | STORED); // `/ru`
+    schema_builder.add_u64_field("rating", INDEXED | STORED | FAST);
+    schema_builder.add_text_field("keywords", TEXT | STORED);
+    schema_builder.add_u64_field("fingerprint", STORED | FAST); // Для инкрементального обновления
     schema_builder.build()
 }
 
+/// Разбор даты INP вида `2007-12-05`
+fn parse_inp_date(value: &str) -> Option<DateTime> {
+    let mut parts = value.trim().splitn(3, '-');
+    let year = parts.next()?.parse::<i32>().ok()?;
+    let month = parts.next()?.parse::<u8>().ok()?;
+    let day = parts.next()?.parse::<u8>().ok()?;
+    let date = Date::from_calendar_date(year, Month::try_from(month).ok()?, day).ok()?;
+    Some(DateTime::from_primitive(date.midnight()))
+}
+
+/// Форматирование даты обратно в вид `YYYY-MM-DD`
+fn format_inp_date(value: DateTime) -> String {
+    let date = value.into_primitive().date();
+    format!(
+        "{:04}-{:02}-{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    )
+}
+
+/// Раскладка колонок Flibusta, используется, если в INPX нет `structure.info`
+const DEFAULT_STRUCTURE: &str =
+    "AUTHOR;GENRE;TITLE;SERIES;SERNO;FILE;SIZE;LIBID;DEL;EXT;DATE;LANG;LIBRATE;KEYWORDS";
+
+/// Раскладка колонок .inp файла: имя колонки -> её позиция в строке
+#[derive(Debug, Clone)]
+struct InpLayout {
+    columns: HashMap<String, usize>,
+    min_fields: usize, // Минимальное число полей в корректной строке
+}
+
+impl InpLayout {
+    /// Разбор содержимого `structure.info` (`AUTHOR;GENRE;TITLE;...;`)
+    fn parse(structure: &str) -> Result<Self, String> {
+        let columns: HashMap<String, usize> = structure
+            .trim()
+            .trim_end_matches(';')
+            .split(';')
+            .enumerate()
+            .map(|(i, name)| (name.trim().to_uppercase(), i))
+            .filter(|(name, _)| !name.is_empty())
+            .collect();
+        let mut min_fields = 0;
+        for required in ["AUTHOR", "TITLE", "FILE"] {
+            let i = columns.get(required).ok_or_else(|| {
+                format!(
+                    "В structure.info нет обязательной колонки {}: '{}'",
+                    required,
+                    structure.trim()
+                )
+            })?;
+            min_fields = min_fields.max(i + 1);
+        }
+        Ok(InpLayout {
+            columns,
+            min_fields,
+        })
+    }
+
+    /// Значение колонки `name` в строке, пустая строка если колонки нет
+    fn get<'a>(&self, fields: &[&'a str], name: &str) -> &'a str {
+        self.columns
+            .get(name)
+            .and_then(|&i| fields.get(i))
+            .map(|s| s.trim())
+            .unwrap_or("")
+    }
+}
+
+impl Default for InpLayout {
+    fn default() -> Self {
+        InpLayout::parse(DEFAULT_STRUCTURE).expect("раскладка по умолчанию корректна")
+    }
+}
+
+/// Разбор строки .inp файла в книгу согласно раскладке колонок
+fn parse_book(layout: &InpLayout, fields: &[&str], zip_archive: String) -> Result<Book, String> {
+    // Используем колонку FILE как `id`
+    let file = layout.get(fields, "FILE");
+    let id = file
+        .parse::<u64>()
+        .map_err(|e| format!("Не удалось распарсить ID '{}': {}", file, e))?;
+    let column = |name: &str| layout.get(fields, name);
+    let number = |name: &str| column(name).parse::<u64>().unwrap_or(0);
+    Ok(Book {
+        id,
+        authors: Author::parse_list(column("AUTHOR")),
+        book_title: column("TITLE").to_string(),
+        // Коды жанров, как и EXT с LANG, в индексе в нижнем регистре
+        genres: column("GENRE")
+            .split(':')
+            .map(str::trim)
+            .filter(|g| !g.is_empty())
+            .map(str::to_lowercase)
+            .collect(),
+        series: column("SERIES").to_string(),
+        serno: number("SERNO"),
+        size: number("SIZE"),
+        libid: number("LIBID"),
+        deleted: column("DEL") == "1",
+        ext: column("EXT").to_lowercase(),
+        date: column("DATE").to_string(),
+        lang: column("LANG").to_lowercase(),
+        rating: number("LIBRATE"),
+        keywords: column("KEYWORDS").to_string(),
+        zip_archive,
+    })
+}
+
 /// Открытие или создание индекса Tantivy
 fn open_or_create_index(index_path: &str) -> Result<Index, TantivyError> {
     if Path::new(index_path).exists() {
-        println!("Открываем существующий индекс из '{}'", index_path);
         Index::open_in_dir(index_path)
     } else {
-        println!("Создаём новый индекс в '{}'", index_path);
-
         // Создаём директорию для индекса
-        fs::create_dir_all(index_path).map_err(|e| TantivyError::from(e))?;
+        fs::create_dir_all(index_path).map_err(TantivyError::from)?;
 
         let schema = create_schema();
         Index::create_in_dir(index_path, schema)
     }
 }
 
-/// Индексация данных из .inpx файла
-fn build_tantivy_index<P: AsRef<Path>>(
-    inpx_path: P,
-    index_path: &str,
-    zip_archives_dir: P,
-) -> Result<(), Box<dyn Error>> {
-    let index = open_or_create_index(index_path)?;
-    let schema = index.schema();
-    let id_field = schema.get_field("id").unwrap();
-    let author_field = schema.get_field("author").unwrap();
-    let title_field = schema.get_field("title").unwrap();
-    let zip_archive_field = schema.get_field("zip_archive").unwrap();
-    let mut writer = index.writer(50_000_000)?; // 50 MB
-
-    let file = File::open(&inpx_path).map_err(|e| {
+/// Файл, который Tantivy или сама библиотека создаёт в каталоге индекса:
+/// `meta.json`, служебные `.managed.json` и `.tantivy-*.lock`, файлы сегментов
+/// `{uuid}.{ext}` / `{uuid}.{opstamp}.del` и каталог авторов
+fn is_index_file(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or("");
+    name == "meta.json"
+        || name == ".managed.json"
+        || (name.starts_with(".tantivy-") && name.ends_with(".lock"))
+        || name.starts_with(catalog::CATALOG_FILE)
+        || (stem.len() == 32 && stem.chars().all(|c| c.is_ascii_hexdigit()) && stem != name)
+}
+
+/// Индекс для полной перестройки. Пустой каталог, как и индекс со старой схемой
+/// или повреждённый индекс, пересоздаётся: его документы всё равно были бы удалены.
+/// Удаляются только файлы индекса; каталог, который не похож на индекс
+/// (нет `meta.json`, но есть другие файлы), не трогается
+fn open_for_full_build(index_path: &str) -> Result<Index, TantivyError> {
+    let path = Path::new(index_path);
+    if !path.exists() {
+        return open_or_create_index(index_path);
+    }
+    if let Ok(index) = Index::open_in_dir(index_path) {
+        if index.schema() == create_schema() {
+            return Ok(index);
+        }
+    }
+    let entries = fs::read_dir(path)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .map_err(TantivyError::from)?;
+    if !entries.is_empty() && !path.join("meta.json").exists() {
+        return Err(TantivyError::InvalidArgument(format!(
+            "Каталог '{}' не пуст и не является индексом, укажите другой путь",
+            index_path
+        )));
+    }
+    for entry in entries {
+        if is_index_file(&entry.file_name().to_string_lossy()) {
+            fs::remove_file(entry.path()).map_err(TantivyError::from)?;
+        }
+    }
+    Index::create_in_dir(index_path, create_schema())
+}
+
+/// Разбор названия политики перезагрузки ридера
+fn parse_reload_policy(policy: &str) -> Result<ReloadPolicy, String> {
+    match policy {
+        "on_commit" => Ok(ReloadPolicy::OnCommit),
+        "manual" => Ok(ReloadPolicy::Manual),
+        _ => Err(format!(
+            "Неизвестная политика перезагрузки '{}', ожидается 'on_commit' или 'manual'",
+            policy
+        )),
+    }
+}
+
+/// Открытый индекс: держим его, общий ридер и хэндлы полей между вызовами
+struct OpenIndex {
+    path: String,
+    index: Index,
+    reader: IndexReader,
+    fields: BookFields,
+    // Каталог авторов и время изменения его файла; перечитывается, если файл обновился
+    authors: RwLock<Option<(SystemTime, Arc<AuthorCatalog>)>>,
+}
+
+impl OpenIndex {
+    fn new(path: &str, index: Index, reload_policy: ReloadPolicy) -> Result<Self, TantivyError> {
+        // Через OpenIndex проходят и запись, и поиск, так что анализаторы
+        // одинаковы при индексации и разборе запросов
+        analyzer::register_tokenizers(&index);
+        let fields = BookFields::from_schema(&index.schema())?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(reload_policy)
+            .try_into()?;
+        Ok(OpenIndex {
+            path: path.to_string(),
+            index,
+            reader,
+            fields,
+            authors: RwLock::new(None),
+        })
+    }
+
+    /// Каталог авторов, построенный при последней сборке индекса
+    fn author_catalog(&self) -> Result<Arc<AuthorCatalog>, Box<dyn Error>> {
+        let path = Path::new(&self.path);
+        let modified = AuthorCatalog::modified(path)?;
+        if let Some((cached_at, catalog)) = self.authors.read().map_err(|e| e.to_string())?.as_ref()
+        {
+            if *cached_at == modified {
+                return Ok(catalog.clone());
+            }
+        }
+        let catalog = Arc::new(AuthorCatalog::load(path)?);
+        *self.authors.write().map_err(|e| e.to_string())? = Some((modified, catalog.clone()));
+        Ok(catalog)
+    }
+}
+
+/// Отчёт о построении индекса: сколько книг проиндексировано и что пропущено
+#[pyclass(get_all)]
+#[derive(Debug, Clone, Default)]
+struct BuildReport {
+    indexed: u64,                             // Проиндексировано книг
+    added: u64,                               // Инкрементальный режим: новых книг
+    updated: u64,         // Инкрементальный режим: книг с изменёнными метаданными
+    removed: u64,         // Инкрементальный режим: книг, исчезнувших из INPX
+    unchanged: u64,       // Инкрементальный режим: книг без изменений
+    kept: u64,            // Книг оставлено в индексе как было: их zip-архив не найден
+    too_few_fields: u64,  // Строк пропущено: недостаточно полей
+    invalid_id: u64,      // Строк пропущено: не удалось распарсить ID
+    missing_archive: u64, // Строк пропущено: нет zip-архива
+    duplicate_id: u64,    // Строк пропущено: ID уже встречался
+    missing_archives: Vec<String>, // Имена отсутствующих zip-архивов
+    duplicate_ids: Vec<u64>, // Повторяющиеся ID (без повторов)
+    unreadable_files: Vec<String>, // Файлы INPX, которые не удалось прочитать
+    bad_lines: Vec<(String, String, String)>, // Первые проблемные строки: (файл, причина, строка)
+    elapsed_secs: f64,
+}
+
+impl BuildReport {
+    /// Запоминает проблемную строку, если лимит образцов ещё не исчерпан
+    fn record_bad_line(&mut self, limit: usize, file: &str, reason: &str, line: &str) {
+        if self.bad_lines.len() < limit {
+            self.bad_lines
+                .push((file.to_string(), reason.to_string(), line.to_string()));
+        }
+    }
+}
+
+#[pymethods]
+impl BuildReport {
+    /// Общее число пропущенных строк
+    #[getter]
+    fn skipped(&self) -> u64 {
+        self.too_few_fields + self.invalid_id + self.missing_archive + self.duplicate_id
+    }
+
+    fn __repr__(&self) -> String {
         format!(
-            "Не удалось открыть файл '{}': {}",
-            inpx_path.as_ref().display(),
-            e
+            "BuildReport(indexed={}, added={}, updated={}, removed={}, unchanged={}, kept={}, skipped={}, missing_archives={}, duplicate_ids={}, elapsed_secs={:.2})",
+            self.indexed,
+            self.added,
+            self.updated,
+            self.removed,
+            self.unchanged,
+            self.kept,
+            self.skipped(),
+            self.missing_archives.len(),
+            self.duplicate_ids.len(),
+            self.elapsed_secs
         )
-    })?;
+    }
+}
+
+/// Чтение книг из .inpx файла. Пропущенные строки учитываются в `report`,
+/// первые `bad_lines_limit` из них сохраняются целиком.
+/// Вместе с книгами возвращаются `id` строк, пропущенных из-за отсутствующего архива
+fn read_inpx_books(
+    inpx_path: &Path,
+    zip_archives_dir: &Path,
+    report: &mut BuildReport,
+    bad_lines_limit: usize,
+) -> Result<(Vec<Book>, HashSet<u64>), Box<dyn Error>> {
+    let file = File::open(inpx_path)
+        .map_err(|e| format!("Не удалось открыть файл '{}': {}", inpx_path.display(), e))?;
     let mut archive = ZipArchive::new(file).map_err(|e| {
         format!(
             "Не удалось создать ZipArchive из '{}': {}",
-            inpx_path.as_ref().display(),
+            inpx_path.display(),
             e
         )
     })?;
-    let mut contents_vec: Vec<String> = Vec::new();
-
-    // Получаем директорию, где лежит inpx файл, для построения пути к zip-архивам
-    let inpx_path = Path::new(inpx_path.as_ref());
-    let zip_archives_dir = Path::new(zip_archives_dir.as_ref());
+    // Пары (имя .inp файла, содержимое)
+    let mut contents_vec: Vec<(String, String)> = Vec::new();
+    let mut structure: Option<String> = None;
 
     // Сбор всех содержимых .inp файлов
     for i in 0..archive.len() {
         let mut inp_file = match archive.by_index(i) {
             Ok(f) => f,
             Err(e) => {
-                println!(
-                    "Не удалось получить файл по индексу {} в архиве '{}': {}",
-                    i,
-                    inpx_path.display(),
-                    e
-                );
+                report.unreadable_files.push(format!("#{}: {}", i, e));
                 continue;
             }
         };
-        if !inp_file.name().ends_with(".inp") {
+        let is_structure = inp_file.name().eq_ignore_ascii_case("structure.info");
+        if !inp_file.name().ends_with(".inp") && !is_structure {
             continue;
         }
         let mut contents = String::new();
         if inp_file.read_to_string(&mut contents).is_err() {
-            println!(
-                "Не удалось прочитать содержимое файла '{}'",
-                inp_file.name()
-            );
+            report.unreadable_files.push(inp_file.name().to_string());
             continue;
         }
-        contents_vec.push(contents);
+        if is_structure {
+            structure = Some(contents);
+        } else {
+            contents_vec.push((inp_file.name().to_string(), contents));
+        }
     }
 
+    // Раскладка колонок из `structure.info`, либо раскладка Flibusta по умолчанию
+    let layout = match structure {
+        Some(structure) => InpLayout::parse(&structure)?,
+        None => InpLayout::default(),
+    };
+
     // Последовательная обработка содержимого .inp файлов для извлечения книг
     let mut books: Vec<Book> = Vec::new();
-    for (i, contents) in contents_vec.iter().enumerate() {
-        for line in contents.lines() {
-            let fields: Vec<&str> = line.trim_end_matches('\n').split('\x04').collect();
-            if fields.len() >= 11 {
-                // Убедимся, что достаточно полей
-                // Извлечение `id` и построение пути к zip-архиву
-                let id = match fields[5].parse::<u64>() {
-                    // Используем field[5] как `id`
-                    Ok(num) => num,
-                    Err(e) => {
-                        println!(
-                            "Не удалось распарсить ID '{}' в файле {}: {}",
-                            fields[5], i, e
-                        );
-                        continue;
-                    }
-                };
+    // Кэш проверки существования zip-архивов
+    let mut archive_exists: HashMap<String, bool> = HashMap::new();
+    let mut seen_ids: HashSet<u64> = HashSet::new();
+    let mut missing_ids: HashSet<u64> = HashSet::new();
+    for (inp_file_name, contents) in contents_vec.iter() {
+        // По умолчанию архив называется как .inp файл: заменяем ".inp" на ".zip"
+        let default_zip_name = inp_file_name.trim_end_matches(".inp").to_string() + ".zip";
 
-                // Извлечение имени .inp файла для построения имени zip-архива
-                let zip_file = archive
-                    .by_index(i)
-                    .map_err(|e| format!("Не удалось получить файл по индексу {}: {}", i, e))?;
-                let inp_file_name = zip_file.name(); // Получаем имя текущего .inp файла
+        for line in contents.lines() {
+            let fields: Vec<&str> = line.trim_end_matches('\r').split('\x04').collect();
+            if fields.len() < layout.min_fields {
+                report.too_few_fields += 1;
+                report.record_bad_line(
+                    bad_lines_limit,
+                    inp_file_name,
+                    "Недостаточно полей в строке",
+                    line,
+                );
+                continue;
+            }
 
-                // Проверяем, что имя заканчивается на ".inp"
-                if !inp_file_name.ends_with(".inp") {
-                    println!("Имя файла '{}' не заканчивается на '.inp'", inp_file_name);
-                    continue;
+            // Колонка FOLDER (MyHomeLib) переопределяет имя архива для отдельной книги
+            let zip_file_name = match layout.get(&fields, "FOLDER") {
+                "" => default_zip_name.clone(),
+                folder if folder.ends_with(".zip") => folder.to_string(),
+                folder => format!("{}.zip", folder),
+            };
+            // Строим полный путь к zip-архиву
+            let zip_archive_path = zip_archives_dir
+                .join(&zip_file_name)
+                .to_string_lossy()
+                .to_string();
+
+            // Проверяем, существует ли zip-архив
+            let exists = *archive_exists
+                .entry(zip_archive_path.clone())
+                .or_insert_with(|| {
+                    let exists = Path::new(&zip_archive_path).exists();
+                    if !exists {
+                        report.missing_archives.push(zip_file_name.clone());
+                    }
+                    exists
+                });
+            if !exists {
+                report.missing_archive += 1;
+                if let Ok(id) = layout.get(&fields, "FILE").parse::<u64>() {
+                    missing_ids.insert(id);
                 }
+                continue;
+            }
 
-                // Заменяем ".inp" на ".zip"
-                let zip_file_name = inp_file_name.trim_end_matches(".inp").to_string() + ".zip";
-                // Строим полный путь к zip-архиву
-                let zip_archive_path = zip_archives_dir
-                    .join(&zip_file_name)
-                    .to_string_lossy()
-                    .to_string();
-
-                // Проверяем, существует ли zip-архив
-                if !Path::new(&zip_archive_path).exists() {
-                    // println!("Zip-архив '{}' не существует. Пропускаем запись с ID {}", zip_archive_path, id);
+            // В индексе хранится только имя архива: каталог с архивами можно перенести
+            let book = match parse_book(&layout, &fields, zip_file_name) {
+                Ok(book) => book,
+                Err(e) => {
+                    report.invalid_id += 1;
+                    report.record_bad_line(bad_lines_limit, inp_file_name, &e, line);
                     continue;
                 }
-
-                // Создаём структуру Book с полями `id`, `author_name`, `book_title`, `zip_archive` и `internal_file_name`
-                books.push(Book {
-                    id,
-                    author_name: fields[0].to_string(),
-                    book_title: fields[2].to_string(),
-                    zip_archive: zip_archive_path,
-                });
-            } else {
-                println!("Недостаточно полей в строке: '{}'", line);
+            };
+            // Повторный ID оставил бы в индексе две книги с одним `id`, берём первую
+            if !seen_ids.insert(book.id) {
+                if !report.duplicate_ids.contains(&book.id) {
+                    report.duplicate_ids.push(book.id);
+                }
+                report.duplicate_id += 1;
+                report.record_bad_line(bad_lines_limit, inp_file_name, "Повторяющийся ID", line);
+                continue;
             }
+            books.push(book);
         }
     }
 
-    println!("Индексация {} книг...", books.len());
+    // Книга с архивом в другой строке INPX не считается пропавшей
+    missing_ids.retain(|id| !seen_ids.contains(id));
+    Ok((books, missing_ids))
+}
+
+/// Отпечатки книг, уже лежащих в индексе: `id` -> fingerprint
+fn indexed_fingerprints(idx: &OpenIndex) -> Result<HashMap<u64, u64>, TantivyError> {
+    // Ридер может ещё не видеть последний коммит при ручной политике перезагрузки
+    idx.reader.reload()?;
+    let searcher = idx.reader.searcher();
+    let fields = idx.fields;
+    let mut fingerprints = HashMap::new();
+    for segment_reader in searcher.segment_readers() {
+        let ids = segment_reader.fast_fields().u64(fields.id)?;
+        let prints = segment_reader.fast_fields().u64(fields.fingerprint)?;
+        for doc in segment_reader.doc_ids_alive() {
+            fingerprints.insert(ids.get_val(doc), prints.get_val(doc));
+        }
+    }
+    Ok(fingerprints)
+}
 
-    // Индексация каждой книги в Tantivy
-    for book in books {
-        let mut doc = TantivyDocument::new();
-        doc.add_u64(id_field, book.id); // Добавляем `id`
-        doc.add_text(author_field, &book.author_name);
-        doc.add_text(title_field, &book.book_title);
-        doc.add_text(zip_archive_field, &book.zip_archive); // Добавляем `zip_archive`
-        writer.add_document(doc)?;
+/// Индексация данных из .inpx файла.
+/// В полном режиме индекс перестраивается с нуля, в инкрементальном - сравнивается
+/// с INPX по `id`: новые книги добавляются, исчезнувшие удаляются, изменённые переиндексируются
+fn build_tantivy_index<P: AsRef<Path>>(
+    idx: &OpenIndex,
+    inpx_path: P,
+    zip_archives_dir: P,
+    bad_lines_limit: usize,
+    incremental: bool,
+) -> Result<BuildReport, Box<dyn Error>> {
+    let started = Instant::now();
+    let mut report = BuildReport::default();
+    if incremental && idx.index.schema() != create_schema() {
+        return Err("Схема индекса устарела, нужна полная перестройка (incremental=False)".into());
+    }
+
+    let fields = idx.fields;
+    let mut writer = idx.index.writer(50_000_000)?; // 50 MB
+
+    let (books, missing_ids) = read_inpx_books(
+        inpx_path.as_ref(),
+        zip_archives_dir.as_ref(),
+        &mut report,
+        bad_lines_limit,
+    )?;
+
+    // Книги, чей архив не найден, остаются в индексе как были: неверный или
+    // не смонтированный `zip_archives_dir` не должен опустошать индекс
+    let mut existing = indexed_fingerprints(idx)?;
+    let mut kept = Vec::new();
+    for id in missing_ids {
+        if existing.remove(&id).is_some() {
+            kept.push(idx.fields.read_book(&find_book_doc(idx, id)?));
+            report.kept += 1;
+        }
+    }
+    // Каталог строится по всем книгам индекса, поэтому и в инкрементальном режиме он полный
+    let catalog = AuthorCatalog::from_books(books.iter().chain(&kept));
+
+    if incremental {
+        for book in books {
+            match existing.remove(&book.id) {
+                Some(fingerprint) if fingerprint == book.fingerprint() => {
+                    report.unchanged += 1;
+                }
+                Some(_) => {
+                    // Удаление применяется только к документам, добавленным до него
+                    writer.delete_term(Term::from_field_u64(fields.id, book.id));
+                    writer.add_document(fields.book_document(&book))?;
+                    report.updated += 1;
+                }
+                None => {
+                    writer.add_document(fields.book_document(&book))?;
+                    report.added += 1;
+                }
+            }
+            report.indexed += 1;
+        }
+        // Всё, что осталось, отсутствует в новом INPX
+        for id in existing.into_keys() {
+            writer.delete_term(Term::from_field_u64(fields.id, id));
+            report.removed += 1;
+        }
+    } else {
+        // Полная перестройка: старые документы иначе продублировались бы
+        for id in existing.into_keys() {
+            writer.delete_term(Term::from_field_u64(fields.id, id));
+        }
+        // Индексация каждой книги в Tantivy
+        for book in books {
+            writer.add_document(fields.book_document(&book))?;
+            report.indexed += 1;
+        }
     }
 
     writer
         .commit()
         .map_err(|e| format!("Не удалось зафиксировать изменения в индексе: {}", e))?;
-    println!("Индексация завершена и сохранена в '{}'", index_path);
-    Ok(())
+    // Результат сборки должен быть виден сразу, не дожидаясь фоновой перезагрузки
+    idx.reader.reload()?;
+    catalog.save(Path::new(&idx.path))?;
+    report.elapsed_secs = started.elapsed().as_secs_f64();
+    Ok(report)
 }
 
-/// Поиск с использованием Tantivy, возвращает (id, author, title, score)
-fn search_tantivy(
-    index_path: &str,
-    query_str: &str,
-) -> Result<Vec<(u64, String, String, f32)>, TantivyError> {
-    let index = Index::open_in_dir(index_path)?;
-    let reader = index.reader()?;
-    let searcher = reader.searcher();
-
-    let schema = index.schema();
-    let id_field = schema.get_field("id").unwrap();
-    let author_field = schema.get_field("author").unwrap();
-    let title_field = schema.get_field("title").unwrap();
-
-    let query_parser = QueryParser::for_index(&index, vec![author_field, title_field]);
-    let query = query_parser.parse_query(query_str)?;
+/// Граница диапазона из необязательного значения
+fn bound<T>(value: Option<T>) -> std::ops::Bound<T> {
+    value.map_or(std::ops::Bound::Unbounded, std::ops::Bound::Included)
+}
 
-    // Получение топ 10 результатов
-    let top_docs = searcher.search(&query, &TopDocs::with_limit(10))?;
+/// Наложение фильтров на запрос: фильтры обязательны, но дают нулевой вклад в score
+fn apply_filters(
+    fields: &BookFields,
+    query: Box<dyn Query>,
+    filters: &SearchFilters,
+) -> Box<dyn Query> {
+    let mut filter_queries: Vec<Box<dyn Query>> = Vec::new();
+    for (field, values) in [(fields.lang, &filters.langs), (fields.ext, &filters.exts)] {
+        if !values.is_empty() {
+            let terms = values
+                .iter()
+                .map(|value| Term::from_facet(field, &Facet::from_path([value.as_str()])));
+            filter_queries.push(Box::new(TermSetQuery::new(terms)));
+        }
+    }
+    if filters.date_from.is_some() || filters.date_to.is_some() {
+        let term = |date| Term::from_field_date(fields.date, date);
+        filter_queries.push(Box::new(RangeQuery::new_term_bounds(
+            fields.date,
+            Type::Date,
+            &bound(filters.date_from.map(term)),
+            &bound(filters.date_to.map(term)),
+        )));
+    }
+    if filters.size_min.is_some() || filters.size_max.is_some() {
+        filter_queries.push(Box::new(RangeQuery::new_u64_bounds(
+            fields.size,
+            bound(filters.size_min),
+            bound(filters.size_max),
+        )));
+    }
+    if let Some(min_rating) = filters.min_rating {
+        filter_queries.push(Box::new(RangeQuery::new_u64_bounds(
+            fields.rating,
+            bound(Some(min_rating)),
+            bound(None),
+        )));
+    }
 
-    let mut results = Vec::new();
+    let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Must, query)];
+    for filter in filter_queries {
+        clauses.push((Occur::Must, Box::new(ConstScoreQuery::new(filter, 0.0))));
+    }
+    Box::new(BooleanQuery::new(clauses))
+}
 
-    for (_score, doc_address) in top_docs {
-        let retrieved_doc: TantivyDocument = searcher.doc(doc_address)?;
+/// Исключение из запроса книг, помеченных как удалённые
+fn exclude_deleted(fields: &BookFields, query: Box<dyn Query>) -> Box<dyn Query> {
+    let deleted = TermQuery::new(
+        Term::from_field_bool(fields.deleted, true),
+        IndexRecordOption::Basic,
+    );
+    Box::new(BooleanQuery::new(vec![
+        (Occur::Must, query),
+        (Occur::MustNot, Box::new(deleted)),
+    ]))
+}
 
-        let id = retrieved_doc
-            .get_first(id_field)
-            .and_then(|v| v.as_u64())
-            .unwrap_or(0);
-        let author = retrieved_doc
-            .get_first(author_field)
-            .and_then(|v| v.as_text())
-            .unwrap_or("")
-            .to_string();
-        let title = retrieved_doc
-            .get_first(title_field)
-            .and_then(|v| v.as_text())
-            .unwrap_or("")
-            .to_string();
-        let score = _score; // BM25 score
-        results.push((id, author, title, score));
+/// Допустимое число опечаток в терме: короткие слова ищутся точно, длинные - с двумя правками
+fn fuzzy_distance(term: &str) -> u8 {
+    match term.chars().count() {
+        0..=3 => 0,
+        4..=6 => 1,
+        _ => 2,
     }
+}
 
-    Ok(results)
+/// Запрос терма в поле: при `fuzzy` - с опечатками по `fuzzy_distance`, иначе точный.
+/// Нечёткий запрос содержит и точный терм с большим весом: по нему точные совпадения
+/// ранжируются выше, а подсветка узнаёт исходное слово
+fn term_query(field: Field, text: &str, fuzzy: bool) -> Box<dyn Query> {
+    let distance = if fuzzy { fuzzy_distance(text) } else { 0 };
+    let term = Term::from_field_text(field, text);
+    let exact = Box::new(TermQuery::new(term.clone(), IndexRecordOption::WithFreqs));
+    if distance == 0 {
+        return exact;
+    }
+    Box::new(BooleanQuery::new(vec![
+        (Occur::Should, Box::new(BoostQuery::new(exact, 2.0))),
+        (
+            Occur::Should,
+            Box::new(FuzzyTermQuery::new(term, distance, true)),
+        ),
+    ]))
 }
-fn get_info(index_path: &str, id: u64) -> Result<(String, String), Box<dyn Error>> {
-    println!("Пытаемся скачать книгу с ID: {}", id);
-    println!("Путь к индексу: {}", index_path);
 
-    // Открываем индекс Tantivy
-    let index = Index::open_in_dir(index_path)
-        .map_err(|e| format!("Не удалось открыть индекс в '{}': {}", index_path, e))?;
+/// Нечёткий вариант разобранного запроса: каждый терм автора и названия (в том числе
+/// их латинских написаний) ищется точно
+/// или по расстоянию Левенштейна, а структура запроса (`+`, `-`, `AND`, поля) сохраняется.
+/// Точные совпадения получают больший вес, чем нечёткие; фразы и термы с `^` остаются точными
+fn fuzzy_query(fields: &BookFields, query: &dyn Query) -> Box<dyn Query> {
+    if let Some(boolean) = query.downcast_ref::<BooleanQuery>() {
+        let clauses = boolean
+            .clauses()
+            .iter()
+            .map(|(occur, clause)| (*occur, fuzzy_query(fields, clause.as_ref())))
+            .collect();
+        return Box::new(BooleanQuery::new(clauses));
+    }
+    if let Some(leaf) = query.downcast_ref::<TermQuery>() {
+        let term = leaf.term();
+        let text = term.as_str().filter(|text| fuzzy_distance(text) > 0);
+        let text_fields = [
+            fields.author,
+            fields.title,
+            fields.author_translit,
+            fields.title_translit,
+        ];
+        if let Some(text) = text.filter(|_| text_fields.contains(&term.field())) {
+            return term_query(term.field(), text, true);
+        }
+    }
+    query.box_clone()
+}
 
-    // Создаём ридер и поисковик
-    let reader = index
-        .reader()
-        .map_err(|e| format!("Не удалось создать ридер для индекса: {}", e))?;
-    let searcher = reader.searcher();
+/// Дополнение запроса поиском по латинским написаниям автора и названия: запрос
+/// романизируется теми же схемами, что и индекс, и каждое написание - отдельная
+/// альтернатива всему запросу, требующая все свои слова. Запросы с операторами
+/// не дополняются: в латинской записи их смысл не сохранить
+fn with_translit_matches(
+    idx: &OpenIndex,
+    query: Box<dyn Query>,
+    query_str: &str,
+) -> Box<dyn Query> {
+    if query_syntax::has_operators(query_str) {
+        return query;
+    }
+    let fields = idx.fields;
+    let mut parser = QueryParser::for_index(
+        &idx.index,
+        vec![fields.author_translit, fields.title_translit],
+    );
+    parser.set_conjunction_by_default();
+    let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Should, query)];
+    for variant in translit::romanize_variants(query_str) {
+        // Написание с непарсящимися символами просто пропускаем
+        if let Ok(alternative) = parser.parse_query(&variant) {
+            clauses.push((Occur::Should, alternative));
+        }
+    }
+    Box::new(BooleanQuery::new(clauses))
+}
 
-    let schema = index.schema();
+/// Термы текста после анализатора, которым индексируется поле
+fn field_terms(idx: &OpenIndex, field: Field, text: &str) -> Result<Vec<String>, TantivyError> {
+    let mut terms = Vec::new();
+    idx.index
+        .tokenizer_for_field(field)?
+        .token_stream(text)
+        .process(&mut |token: &Token| terms.push(token.text.clone()));
+    Ok(terms)
+}
 
-    // Получаем поля из схемы
-    let id_field = schema
-        .get_field("id")
-        .ok_or("Поле 'id' не найдено в схеме индекса")?;
-    let title = schema
-        .get_field("title")
-        .ok_or("Поле 'zip_archive' не найдено в схеме индекса")?;
-    let author = schema
-        .get_field("author")
-        .ok_or("Поле 'zip_archive' не найдено в схеме индекса")?;
+/// Запрос, требующий все термы в поле; `None`, если термов нет
+fn all_terms_query(field: Field, terms: &[String], fuzzy: bool) -> Option<Box<dyn Query>> {
+    if terms.is_empty() {
+        return None;
+    }
+    let clauses = terms
+        .iter()
+        .map(|text| (Occur::Must, term_query(field, text, fuzzy)))
+        .collect();
+    Some(Box::new(BooleanQuery::new(clauses)))
+}
 
-    // Создаём запрос для конкретного `id` с использованием `Basic` опции
-    let query = tantivy::query::TermQuery::new(
-        tantivy::Term::from_field_u64(id_field, id),
-        IndexRecordOption::Basic,
-    );
-
-    // Выполняем поиск
-    let addr = searcher
-        .search(&query, &TopDocs::with_limit(1))
-        .map_err(|e| format!("Ошибка при поиске ID {}: {}", id, e))?
-        .first()
-        .ok_or("С индексом проблемы!")?
-        .1;
-    let retrieved_doc = searcher.doc(addr)?;
-    let title_str = retrieved_doc.get_first(title).and_then(|v| v.as_text()).ok_or("Поле 'title' отсутствует в документе")?.to_string();
-    let author_str = retrieved_doc.get_first(author).and_then(|v| v.as_text()).ok_or("Поле 'title' отсутствует в документе")?.to_string();
-    Ok((title_str, author_str))
+/// Совпадение текста с полем: все слова в самом поле либо в одном из латинских написаний
+fn text_match_query(
+    idx: &OpenIndex,
+    field: Field,
+    translit_field: Option<Field>,
+    text: &str,
+    options: &SearchOptions,
+) -> Result<Option<Box<dyn Query>>, TantivyError> {
+    let mut alternatives: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+    let terms = field_terms(idx, field, text)?;
+    if let Some(query) = all_terms_query(field, &terms, options.fuzzy) {
+        alternatives.push((Occur::Should, query));
+    }
+    if let Some(translit_field) = translit_field.filter(|_| options.translit) {
+        for variant in translit::romanize_variants(text) {
+            let terms = field_terms(idx, translit_field, &variant)?;
+            if let Some(query) = all_terms_query(translit_field, &terms, options.fuzzy) {
+                alternatives.push((Occur::Should, query));
+            }
+        }
+    }
+    Ok(match alternatives.len() {
+        0 => None,
+        1 => alternatives.pop().map(|(_, query)| query),
+        _ => Some(Box::new(BooleanQuery::new(alternatives))),
+    })
 }
-/// Функция для скачивания книги по `id` с подробными сообщениями об ошибках
-fn download_file(index_path: &str, id: u64) -> Result<bool, Box<dyn Error>> {
-    println!("Пытаемся скачать книгу с ID: {}", id);
-    println!("Путь к индексу: {}", index_path);
-
-    // Открываем индекс Tantivy
-    let index = Index::open_in_dir(index_path)
-        .map_err(|e| format!("Не удалось открыть индекс в '{}': {}", index_path, e))?;
-
-    // Создаём ридер и поисковик
-    let reader = index
-        .reader()
-        .map_err(|e| format!("Не удалось создать ридер для индекса: {}", e))?;
-    let searcher = reader.searcher();
-
-    let schema = index.schema();
-
-    // Получаем поля из схемы
-    let id_field = schema
-        .get_field("id")
-        .ok_or("Поле 'id' не найдено в схеме индекса")?;
-    let zip_archive_field = schema
-        .get_field("zip_archive")
-        .ok_or("Поле 'zip_archive' не найдено в схеме индекса")?;
-    // let internal_file_name_field = schema
-    //     .get_field("internal_file_name")
-    //     .ok_or("Поле 'internal_file_name' не найдено в схеме индекса")?;
-
-    // Создаём запрос для конкретного `id` с использованием `Basic` опции
-    let query = tantivy::query::TermQuery::new(
-        tantivy::Term::from_field_u64(id_field, id),
-        IndexRecordOption::Basic,
-    );
-
-    // Выполняем поиск
-    let top_docs = searcher
-        .search(&query, &TopDocs::with_limit(1))
-        .map_err(|e| format!("Ошибка при поиске ID {}: {}", id, e))?;
-
-    if let Some((_score, doc_address)) = top_docs.first() {
-        // Получаем документ
-        let retrieved_doc = searcher.doc(*doc_address).map_err(|e| {
-            format!(
-                "Не удалось получить документ по адресу {:?}: {}",
-                doc_address, e
-            )
-        })?;
-
-        // Извлекаем путь к zip-архиву и имя файла внутри архива
-        let zip_archive_str = retrieved_doc
-            .get_first(zip_archive_field)
-            .and_then(|v| v.as_text())
-            .ok_or("Поле 'zip_archive' отсутствует в документе")?;
-
-        let ifn = format!("{}.fb2", id);
-        let internal_file_name = ifn.as_str();
-
-        println!("Найден путь к zip-архиву: {}", zip_archive_str);
-        println!("Найдено имя файла внутри архива: {}", internal_file_name);
-
-        // Создаём PathBuf из строки пути к архиву
-        let zip_archive_path = PathBuf::from(zip_archive_str);
 
-        // Проверяем существование zip-архива
-        if !zip_archive_path.exists() {
-            return Err(format!("Zip-архив '{}' не существует", zip_archive_path.display()).into());
+/// Построение запроса из отдельных полей; пустой запрос совпадает со всеми книгами
+fn field_query(
+    idx: &OpenIndex,
+    query: &FieldQuery,
+    options: &SearchOptions,
+) -> Result<Box<dyn Query>, TantivyError> {
+    let fields = idx.fields;
+    let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+
+    let text_params = [
+        (&query.author, fields.author, Some(fields.author_translit)),
+        (&query.title, fields.title, Some(fields.title_translit)),
+        (&query.series, fields.series, None),
+    ];
+    for (value, field, translit_field) in text_params {
+        if let Some(value) = value {
+            if let Some(query) = text_match_query(idx, field, translit_field, value, options)? {
+                clauses.push((Occur::Must, query));
+            }
         }
+    }
 
-        // Преобразуем путь к архиву в канонический (полный) путь с правильными разделителями
-        let zip_archive_canonical = zip_archive_path.canonicalize().map_err(|e| {
-            format!(
-                "Не удалось получить канонический путь для '{}': {}",
-                zip_archive_path.display(),
-                e
-            )
-        })?;
-
-        // Преобразуем путь к архиву в URL
-        let zip_archive_url = Url::from_file_path(&zip_archive_canonical).map_err(|_| {
-            format!(
-                "Не удалось преобразовать путь '{}' в URL",
-                zip_archive_canonical.display()
-            )
-        })?;
+    let mut facets = Vec::new();
+    let code = |value: &Option<String>| value.as_deref().map(facet_code).filter(|c| !c.is_empty());
+    if let Some(genre) = code(&query.genre) {
+        facets.push((fields.genre, genres::genre_facet(&genre)));
+    }
+    if let Some(group) = code(&query.genre_group) {
+        facets.push((fields.genre, Facet::from_path([group])));
+    }
+    if let Some(lang) = code(&query.lang) {
+        facets.push((fields.lang, Facet::from_path([lang])));
+    }
+    for (field, facet) in facets {
+        let term = Term::from_facet(field, &facet);
+        clauses.push((
+            Occur::Must,
+            Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+        ));
+    }
 
-        // Создаём экземпляр PartialZip
-        let pz = PartialZip::new(&zip_archive_url)
-            .map_err(|e| format!("Не удалось создать PartialZip: {}", e))?;
+    if clauses.is_empty() {
+        return Ok(Box::new(AllQuery));
+    }
+    Ok(Box::new(BooleanQuery::new(clauses)))
+}
 
-        // Проверяем, существует ли файл внутри архива
-        let files_list = pz.list_names();
-        if !files_list.contains(&internal_file_name.to_string()) {
-            return Err(format!(
-                "Файл '{}' не найден в архиве '{}'",
-                internal_file_name, zip_archive_str
-            )
-            .into());
+/// Запрос по отдельным словам текста в авторе и названии, без синтаксиса запросов
+fn plain_text_query(fields: &BookFields, query_str: &str) -> Box<dyn Query> {
+    let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+    for term in analyzer::analyze(query_str) {
+        for field in [fields.author, fields.title] {
+            let term = Term::from_field_text(field, &term);
+            clauses.push((
+                Occur::Should,
+                Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs)),
+            ));
         }
+    }
+    Box::new(BooleanQuery::new(clauses))
+}
 
-        // Определяем имя выходного файла
-        let output_file_name = format!("{}.fb2", id);
-        let output_path = Path::new(".").join(&output_file_name);
-
-        println!(
-            "Извлечение файла '{}' в '{}'",
-            internal_file_name, output_file_name
-        );
+/// Меньше стольких найденных книг - ищем опечатки в запросе
+const FEW_HITS: usize = 3;
 
-        // Открываем выходной файл
-        let mut output_file = File::create(&output_path).map_err(|e| {
-            format!(
-                "Не удалось создать выходной файл '{}': {}",
-                output_path.display(),
-                e
-            )
-        })?;
+/// Поиск с использованием Tantivy, возвращает страницу найденных книг с их score
+/// и исправленный запрос, если найдено меньше `FEW_HITS` книг
+fn search_tantivy(
+    idx: &OpenIndex,
+    query_str: &str,
+    options: &SearchOptions,
+) -> Result<SearchPage, Box<dyn Error>> {
+    let query = parse_search_query(idx, query_str, options)?;
+    let mut page = collect_page(idx, query, options)?;
+    if page.total < FEW_HITS {
+        page.suggestion = spelling_suggestion(idx, query_str, options, page.total)?;
+    }
+    Ok(page)
+}
 
-        // Извлекаем файл и записываем его в выходной файл
-        pz.download_to_write(internal_file_name, &mut output_file)
-            .map_err(|e| {
-                format!(
-                    "Ошибка при извлечении файла '{}': {}",
-                    internal_file_name, e
-                )
-            })?;
+/// Запрос с исправленными опечатками, если по нему находится больше книг, чем по исходному
+fn spelling_suggestion(
+    idx: &OpenIndex,
+    query_str: &str,
+    options: &SearchOptions,
+    total: usize,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let Some(corrected) = spelling::correct(&idx.reader.searcher(), &idx.fields, query_str)? else {
+        return Ok(None);
+    };
+    // Нужно только число книг с теми же фильтрами
+    let count_options = SearchOptions {
+        facets: false,
+        sort: SortOrder::Relevance,
+        highlight: None,
+        limit: 0,
+        offset: 0,
+        ..options.clone()
+    };
+    let query = parse_search_query(idx, &corrected, &count_options)?;
+    let corrected_total = collect_page(idx, query, &count_options)?.total;
+    Ok((corrected_total > total).then_some(corrected))
+}
 
-        println!(
-            "Файл '{}' успешно извлечён в '{}'",
-            internal_file_name, output_file_name
-        );
-        Ok(true)
-    } else {
-        // Если документ с данным `id` не найден
-        Err(format!("Книга с ID {} не найдена в индексе '{}'", id, index_path).into())
+/// Разбор строки запроса с нечёткими и транслитерированными совпадениями по настройкам
+fn parse_search_query(
+    idx: &OpenIndex,
+    query_str: &str,
+    options: &SearchOptions,
+) -> Result<Box<dyn Query>, Box<dyn Error>> {
+    let fields = idx.fields;
+
+    let query_parser = QueryParser::for_index(&idx.index, vec![fields.author, fields.title]);
+    let mut query = match query_parser.parse_query(query_str) {
+        Ok(query) => query,
+        Err(e) if options.strict => {
+            let issue = query_syntax::describe(query_str, &e);
+            return Err(Box::new(QuerySyntax {
+                query: query_str.to_string(),
+                position: issue.position,
+                reason: issue.reason,
+            }));
+        }
+        // Непарсящийся запрос (кавычка, двоеточие в названии) ищется как обычный текст
+        Err(_) => plain_text_query(&fields, query_str),
+    };
+    if options.translit {
+        query = with_translit_matches(idx, query, query_str);
     }
+    if options.fuzzy {
+        query = fuzzy_query(&fields, query.as_ref());
+    }
+    Ok(query)
 }
-fn get_file_bytes(index_path: &str, id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
-    println!("Пытаемся скачать книгу с ID: {}", id);
-    println!("Путь к индексу: {}", index_path);
 
-    // Открываем индекс Tantivy
-    let index = Index::open_in_dir(index_path)
-        .map_err(|e| format!("Не удалось открыть индекс в '{}': {}", index_path, e))?;
+/// Структурированный поиск по отдельным полям книги
+fn search_fields(
+    idx: &OpenIndex,
+    query: &FieldQuery,
+    options: &SearchOptions,
+) -> Result<SearchPage, TantivyError> {
+    let query = field_query(idx, query, options)?;
+    collect_page(idx, query, options)
+}
 
-    // Создаём ридер и поисковик
-    let reader = index
-        .reader()
-        .map_err(|e| format!("Не удалось создать ридер для индекса: {}", e))?;
-    let searcher = reader.searcher();
+/// Выполнение запроса: исключение удалённых книг и сбор страницы результатов
+fn collect_page(
+    idx: &OpenIndex,
+    mut query: Box<dyn Query>,
+    options: &SearchOptions,
+) -> Result<SearchPage, TantivyError> {
+    let searcher = idx.reader.searcher();
+    let fields = idx.fields;
+    if !options.filters.is_empty() {
+        query = apply_filters(&fields, query, &options.filters);
+    }
+    if !options.include_deleted {
+        query = exclude_deleted(&fields, query);
+    }
 
-    let schema = index.schema();
+    // TopDocs заранее выделяет память под `offset + limit` документов: страница
+    // за пределами индекса обрезается, иначе огромный `limit` роняет процесс
+    let num_docs = usize::try_from(searcher.num_docs()).unwrap_or(usize::MAX);
+    let offset = options.offset.min(num_docs);
+    let limit = options.limit.min(num_docs - offset);
+
+    // Страница результатов, общее число совпадений и фасеты за один проход
+    let facets_collector = options.facets.then(|| facets::facets_collector(&fields));
+    let (top_docs, total, facet_fruit) = if options.sort == SortOrder::Relevance {
+        let top = (limit > 0).then(|| TopDocs::with_limit(limit).and_offset(offset));
+        searcher.search(&query, &(top, Count, facets_collector))?
+    } else {
+        // Страница отрезается после досортировки, поэтому книги до неё тоже собираются
+        let tweaker = SortTweaker::new(fields, options.sort, options.reverse);
+        let top =
+            (limit > 0).then(|| TopDocs::with_limit(offset + limit).tweak_score(tweaker.clone()));
+        let (sorted, total, facet_fruit) =
+            searcher.search(&query, &(top, Count, facets_collector))?;
+        let top_docs = match sorted {
+            Some(sorted) => Some(tweaker.page(&searcher, query.as_ref(), sorted, offset, limit)?),
+            None => None,
+        };
+        (top_docs, total, facet_fruit)
+    };
+
+    let highlighter = match &options.highlight {
+        Some(markers) => Some(Highlighter::new(
+            &searcher,
+            query.as_ref(),
+            &fields,
+            options.fuzzy,
+            markers.clone(),
+        )?),
+        None => None,
+    };
+
+    let mut hits = Vec::new();
+
+    for (score, doc_address) in top_docs.unwrap_or_default() {
+        let retrieved_doc: TantivyDocument = searcher.doc(doc_address)?;
+        let book = fields.read_book(&retrieved_doc);
+        let snippets = highlighter
+            .as_ref()
+            .map(|h| h.snippets(&book))
+            .unwrap_or_default();
+        hits.push(SearchHit {
+            book,
+            score,
+            snippets,
+        });
+    }
 
-    // Получаем поля из схемы
-    let id_field = schema
-        .get_field("id")
-        .ok_or("Поле 'id' не найдено в схеме индекса")?;
-    let zip_archive_field = schema
-        .get_field("zip_archive")
-        .ok_or("Поле 'zip_archive' не найдено в схеме индекса")?;
-    // let internal_file_name_field = schema
-    //     .get_field("internal_file_name")
-    //     .ok_or("Поле 'internal_file_name' не найдено в схеме индекса")?;
+    Ok(SearchPage {
+        hits,
+        total,
+        offset: options.offset,
+        limit: options.limit,
+        facets: facet_fruit.map(facets::facet_counts).unwrap_or_default(),
+        suggestion: None,
+    })
+}
+/// Поиск документа книги по `id`
+fn find_book_doc(idx: &OpenIndex, id: u64) -> Result<TantivyDocument, Box<dyn Error>> {
+    let searcher = idx.reader.searcher();
 
     // Создаём запрос для конкретного `id` с использованием `Basic` опции
-    let query = tantivy::query::TermQuery::new(
-        tantivy::Term::from_field_u64(id_field, id),
+    let query = TermQuery::new(
+        Term::from_field_u64(idx.fields.id, id),
         IndexRecordOption::Basic,
     );
 
@@ -453,7 +1645,7 @@ fn get_file_bytes(index_path: &str, id: u64) -> Result<Vec<u8>, Box<dyn Error>>
         .map_err(|e| format!("Ошибка при поиске ID {}: {}", id, e))?;
     let (_score, doc_address) = top_docs
         .first()
-        .ok_or_else(|| format!("Книга с ID {} не найдена в индексе '{}'", id, index_path))?;
+        .ok_or_else(|| format!("Книга с ID {} не найдена в индексе '{}'", id, idx.path))?;
     // Получаем документ
     let retrieved_doc = searcher.doc(*doc_address).map_err(|e| {
         format!(
@@ -461,21 +1653,264 @@ fn get_file_bytes(index_path: &str, id: u64) -> Result<Vec<u8>, Box<dyn Error>>
             doc_address, e
         )
     })?;
+    Ok(retrieved_doc)
+}
 
-    // Извлекаем путь к zip-архиву и имя файла внутри архива
-    let zip_archive_str = retrieved_doc
-        .get_first(zip_archive_field)
-        .and_then(|v| v.as_text())
-        .ok_or("Поле 'zip_archive' отсутствует в документе")?;
+fn get_info(idx: &OpenIndex, id: u64) -> Result<Book, Box<dyn Error>> {
+    let retrieved_doc = find_book_doc(idx, id)?;
+    Ok(idx.fields.read_book(&retrieved_doc))
+}
+/// Дерево жанров (группа -> жанр) с количеством книг в каждом
+fn genres_tree(idx: &OpenIndex) -> Result<Vec<GenreGroup>, TantivyError> {
+    let searcher = idx.reader.searcher();
+    let fields = idx.fields;
+
+    // Считаем и группы верхнего уровня, и жанры внутри каждой группы
+    let query = exclude_deleted(&fields, Box::new(AllQuery));
+    let (group_counts, counts) =
+        searcher.search(&query, &facets::genre_collectors(fields.genre))?;
+
+    let mut tree = Vec::new();
+    // Группы выводим в порядке справочника, пустые пропускаем
+    for group in genres::GENRE_GROUPS {
+        let group_facet = Facet::from_path([group.code]);
+        let count = group_counts
+            .get(Facet::root())
+            .find(|(facet, _)| **facet == group_facet)
+            .map(|(_, count)| count)
+            .unwrap_or(0);
+        if count == 0 {
+            continue;
+        }
+        let mut group_genres: Vec<Genre> = counts
+            .get(group_facet)
+            .filter_map(|(facet, count)| {
+                let code = facet.to_path().last()?.to_string();
+                let info = genres::find_genre(&code);
+                Some(Genre {
+                    name_ru: info.map(|g| g.name_ru).unwrap_or(&code).to_string(),
+                    name_en: info.map(|g| g.name_en).unwrap_or(&code).to_string(),
+                    code,
+                    count,
+                })
+            })
+            .collect();
+        // Жанры из справочника идут в его порядке, неизвестные коды - в конце
+        group_genres.sort_by_key(|g| {
+            genres::GENRES
+                .iter()
+                .position(|info| info.code == g.code)
+                .unwrap_or(usize::MAX)
+        });
+        tree.push(GenreGroup {
+            code: group.code.to_string(),
+            name_ru: group.name_ru.to_string(),
+            name_en: group.name_en.to_string(),
+            count,
+            genres: group_genres,
+        });
+    }
 
-    let ifn = format!("{}.fb2", id);
-    let internal_file_name = ifn.as_str();
+    Ok(tree)
+}
 
-    println!("Найден путь к zip-архиву: {}", zip_archive_str);
-    println!("Найдено имя файла внутри архива: {}", internal_file_name);
+/// Запрос всех книг серии по её нормализованному названию
+fn series_query(fields: &BookFields, name: &str) -> TermQuery {
+    let facet = Facet::from_path([collation::normalize(name)]);
+    TermQuery::new(
+        Term::from_facet(fields.series_key, &facet),
+        IndexRecordOption::Basic,
+    )
+}
 
-    // Создаём PathBuf из строки пути к архиву
-    let zip_archive_path = PathBuf::from(zip_archive_str);
+/// Ключ порядка чтения: по номеру в серии, книги без номера - в конце по названию
+fn reading_order(book: &Book) -> (bool, u64, u64, u64) {
+    (
+        book.serno == 0,
+        book.serno,
+        collation::sort_key(&book.book_title),
+        book.id,
+    )
+}
+
+/// Все книги серии в порядке чтения
+fn series_books(
+    idx: &OpenIndex,
+    name: &str,
+    include_deleted: bool,
+) -> Result<Vec<Book>, TantivyError> {
+    let searcher = idx.reader.searcher();
+    let fields = idx.fields;
+    let mut query: Box<dyn Query> = Box::new(series_query(&fields, name));
+    if !include_deleted {
+        query = exclude_deleted(&fields, query);
+    }
+    let mut books = Vec::new();
+    for doc_address in searcher.search(&query, &DocSetCollector)? {
+        let doc: TantivyDocument = searcher.doc(doc_address)?;
+        books.push(fields.read_book(&doc));
+    }
+    books.sort_by_key(reading_order);
+    Ok(books)
+}
+
+/// Поиск серий по словам названия: серии с наибольшим числом найденных книг первыми
+fn search_series(
+    idx: &OpenIndex,
+    query_str: &str,
+    limit: usize,
+    include_deleted: bool,
+) -> Result<Vec<Series>, TantivyError> {
+    let searcher = idx.reader.searcher();
+    let fields = idx.fields;
+    let options = SearchOptions {
+        include_deleted,
+        ..SearchOptions::default()
+    };
+    let mut query = text_match_query(idx, fields.series, None, query_str, &options)?
+        .unwrap_or_else(|| Box::new(AllQuery));
+    if !include_deleted {
+        query = exclude_deleted(&fields, query);
+    }
+
+    let mut collector = FacetCollector::for_field(fields.series_key);
+    collector.add_facet(Facet::root());
+    let counts = searcher.search(&query, &collector)?;
+    let mut found: Vec<(String, u64)> = counts
+        .get(Facet::root())
+        .filter_map(|(facet, count)| Some((facet.to_path().last()?.to_string(), count)))
+        .collect();
+    found.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    found.truncate(limit);
+
+    // Название и авторы - из книг самой серии
+    let mut series = Vec::new();
+    for (key, count) in found {
+        let books = series_books(idx, &key, include_deleted)?;
+        let mut authors: Vec<String> = Vec::new();
+        for author in books.iter().flat_map(|book| &book.authors) {
+            if !authors.contains(&author.name) {
+                authors.push(author.name.clone());
+            }
+        }
+        series.push(Series {
+            name: books.first().map(|b| b.series.clone()).unwrap_or(key),
+            count,
+            authors,
+        });
+    }
+    Ok(series)
+}
+
+/// Все книги автора по ключу (`Author.key`): серии по алфавиту, книги в них
+/// по номеру, затем книги вне серий по названию
+fn author_bibliography(
+    idx: &OpenIndex,
+    author_key: &str,
+    include_deleted: bool,
+) -> Result<Bibliography, TantivyError> {
+    let searcher = idx.reader.searcher();
+    let fields = idx.fields;
+    let key = collation::normalize(author_key);
+    let mut query: Box<dyn Query> = Box::new(TermQuery::new(
+        Term::from_facet(fields.author_key, &Facet::from_path([key.as_str()])),
+        IndexRecordOption::Basic,
+    ));
+    if !include_deleted {
+        query = exclude_deleted(&fields, query);
+    }
+
+    let mut by_series: HashMap<String, Vec<Book>> = HashMap::new();
+    let mut standalone = Vec::new();
+    for doc_address in searcher.search(&query, &DocSetCollector)? {
+        let doc: TantivyDocument = searcher.doc(doc_address)?;
+        let book = fields.read_book(&doc);
+        let series_key = collation::normalize(&book.series);
+        if series_key.is_empty() {
+            standalone.push(book);
+        } else {
+            by_series.entry(series_key).or_default().push(book);
+        }
+    }
+
+    // Документы приходят в произвольном порядке, поэтому название серии
+    // берётся из первой книги в порядке чтения, как в `search_series`
+    let mut series: Vec<SeriesBooks> = by_series
+        .into_values()
+        .map(|mut books| {
+            books.sort_by_key(reading_order);
+            SeriesBooks {
+                name: books[0].series.clone(),
+                books,
+            }
+        })
+        .collect();
+    series.sort_by(|a, b| collation::compare(&a.name, &b.name).then_with(|| a.name.cmp(&b.name)));
+    standalone.sort_by(|a, b| {
+        collation::compare(&a.book_title, &b.book_title).then_with(|| a.id.cmp(&b.id))
+    });
+    // Написание имени - из первой книги после сортировки, чтобы не зависеть от порядка документов
+    let author = series
+        .iter()
+        .flat_map(|s| &s.books)
+        .chain(&standalone)
+        .flat_map(|book| &book.authors)
+        .find(|a| a.key == key)
+        .cloned();
+    Ok(Bibliography {
+        author,
+        series,
+        standalone,
+    })
+}
+
+/// Расширение по умолчанию для записей без колонки EXT
+const DEFAULT_EXT: &str = "fb2";
+
+/// Поиск файла книги внутри архива: сначала `{id}.{ext}`, затем любой `{id}.*`
+/// (в некоторых коллекциях регистр или расширение в INP не совпадают с архивом)
+fn find_in_archive(files_list: &[String], id: u64, expected: &str) -> Option<String> {
+    if files_list.iter().any(|name| name == expected) {
+        return Some(expected.to_string());
+    }
+    let prefix = format!("{}.", id);
+    files_list
+        .iter()
+        .find(|name| name.starts_with(&prefix) && !name[prefix.len()..].contains('/'))
+        .cloned()
+}
+
+/// Открытие zip-архива с книгой по `id` в каталоге `zip_archives_dir`: возвращает
+/// архив, имя файла внутри него и расширение из колонки EXT
+fn open_book_archive(
+    idx: &OpenIndex,
+    zip_archives_dir: &Path,
+    id: u64,
+) -> Result<(PartialZip, String, String), Box<dyn Error>> {
+    let retrieved_doc = find_book_doc(idx, id)?;
+    let book = idx.fields.read_book(&retrieved_doc);
+
+    // Книги, помеченные в INPX как удалённые (DEL=1), не отдаём
+    if book.deleted {
+        return Err(BookDeleted(id).into());
+    }
+
+    // Расширение из колонки EXT определяет имя файла внутри архива
+    let ext = if book.ext.is_empty() {
+        DEFAULT_EXT.to_string()
+    } else {
+        book.ext
+    };
+    let ifn = format!("{}.{}", id, ext);
+    let internal_file_name = ifn.as_str();
+    let zip_archive_str = book.zip_archive.as_str();
+
+    // Путь к архиву в текущем каталоге архивов; индексы прежних версий
+    // хранили полный путь, из него тоже берём только имя файла
+    let zip_archive_name = Path::new(zip_archive_str)
+        .file_name()
+        .ok_or_else(|| format!("Неверное имя zip-архива '{}'", zip_archive_str))?;
+    let zip_archive_path = zip_archives_dir.join(zip_archive_name);
 
     // Проверяем существование zip-архива
     if !zip_archive_path.exists() {
@@ -505,13 +1940,57 @@ fn get_file_bytes(index_path: &str, id: u64) -> Result<Vec<u8>, Box<dyn Error>>
 
     // Проверяем, существует ли файл внутри архива
     let files_list = pz.list_names();
-    if !files_list.contains(&internal_file_name.to_string()) {
-        return Err(format!(
-            "Файл '{}' не найден в архиве '{}'",
-            internal_file_name, zip_archive_str
+    let internal_file_name =
+        find_in_archive(&files_list, id, internal_file_name).ok_or_else(|| {
+            format!(
+                "Файл '{}' не найден в архиве '{}'",
+                internal_file_name, zip_archive_str
+            )
+        })?;
+    Ok((pz, internal_file_name, ext))
+}
+
+/// Функция для скачивания книги по `id` с подробными сообщениями об ошибках
+fn download_file(
+    idx: &OpenIndex,
+    zip_archives_dir: &Path,
+    id: u64,
+) -> Result<bool, Box<dyn Error>> {
+    let (pz, ifn, _ext) = open_book_archive(idx, zip_archives_dir, id)?;
+    let internal_file_name = ifn.as_str();
+
+    // Определяем имя выходного файла
+    let output_file_name = internal_file_name.to_string();
+    let output_path = Path::new(".").join(&output_file_name);
+
+    // Открываем выходной файл
+    let mut output_file = File::create(&output_path).map_err(|e| {
+        format!(
+            "Не удалось создать выходной файл '{}': {}",
+            output_path.display(),
+            e
         )
-        .into());
-    }
+    })?;
+
+    // Извлекаем файл и записываем его в выходной файл
+    pz.download_to_write(internal_file_name, &mut output_file)
+        .map_err(|e| {
+            format!(
+                "Ошибка при извлечении файла '{}': {}",
+                internal_file_name, e
+            )
+        })?;
+
+    Ok(true)
+}
+fn get_file_bytes(
+    idx: &OpenIndex,
+    zip_archives_dir: &Path,
+    id: u64,
+) -> Result<(Vec<u8>, String), Box<dyn Error>> {
+    let (pz, ifn, ext) = open_book_archive(idx, zip_archives_dir, id)?;
+    let internal_file_name = ifn.as_str();
+
     let mut buffer = Vec::new();
     pz.download_to_write(internal_file_name, &mut buffer)
         .map_err(|e| {
@@ -521,26 +2000,95 @@ fn get_file_bytes(index_path: &str, id: u64) -> Result<Vec<u8>, Box<dyn Error>>
             )
         })?;
 
-    // Вся логика остается той же, но вместо записи в файл
-    // Вы создаете буфер в памяти и записываете данные туда
-    Ok(buffer)
+    // Формат берём из фактического имени файла в архиве
+    let format = Path::new(internal_file_name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or(ext);
+    Ok((buffer, format))
 }
 
-/// Структура для инициализации и управления индексом
-#[pyclass]
+/// Структура для инициализации и управления индексом.
+/// Неизменяемая (`frozen`): открытый индекс защищён `RwLock`, поэтому один объект
+/// можно использовать из нескольких потоков Python одновременно
+#[pyclass(frozen)]
 struct FlibRS {
     index_path: String,
     zip_archives_dir: String, // Добавлено поле для директории с архивами
+    reload_policy: ReloadPolicy,
+    // Открытый индекс, либо причина, по которой его нет: не построен, закрыт или не открылся
+    index: RwLock<Result<Arc<OpenIndex>, String>>,
+}
+
+/// Причина отсутствия индекса, пока он не построен через `build_index`
+const NOT_BUILT: &str = "постройте его через build_index";
+
+impl FlibRS {
+    /// Открытый индекс, либо ошибка с причиной, по которой его нет
+    fn open_index(&self) -> PyResult<Arc<OpenIndex>> {
+        self.index
+            .read()
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?
+            .clone()
+            .map_err(|reason| {
+                PyRuntimeError::new_err(format!(
+                    "Индекс '{}' не открыт: {}",
+                    self.index_path, reason
+                ))
+            })
+    }
+
+    /// Открытие индекса с диска функцией `open`
+    fn open_with(
+        &self,
+        open: fn(&str) -> Result<Index, TantivyError>,
+    ) -> Result<Arc<OpenIndex>, String> {
+        let index = open(&self.index_path)
+            .map_err(|e| format!("Не удалось открыть индекс в '{}': {}", self.index_path, e))?;
+        let idx = OpenIndex::new(&self.index_path, index, self.reload_policy)
+            .map_err(|e| e.to_string())?;
+        Ok(Arc::new(idx))
+    }
+
+    /// Открытие индекса с диска функцией `open` и замена текущего
+    fn reopen(&self, open: fn(&str) -> Result<Index, TantivyError>) -> PyResult<Arc<OpenIndex>> {
+        let idx = self.open_with(open).map_err(PyRuntimeError::new_err)?;
+        *self
+            .index
+            .write()
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))? = Ok(idx.clone());
+        Ok(idx)
+    }
 }
 
 #[pymethods]
 impl FlibRS {
+    /// `reload_policy`: `on_commit` - ридер сам подхватывает новые коммиты,
+    /// `manual` - только по явному вызову `reload()`
     #[new]
-    fn new(index_path: String, zip_archives_dir: Option<String>) -> Self {
-        FlibRS {
+    #[pyo3(signature = (index_path, zip_archives_dir=None, reload_policy="on_commit"))]
+    fn new(
+        index_path: String,
+        zip_archives_dir: Option<String>,
+        reload_policy: &str,
+    ) -> PyResult<Self> {
+        let mut flib = FlibRS {
             index_path,
             zip_archives_dir: zip_archives_dir.unwrap_or_else(|| "./archive".to_string()), // Устанавливаем значение по умолчанию
+            reload_policy: parse_reload_policy(reload_policy).map_err(PyValueError::new_err)?,
+            index: RwLock::new(Err(NOT_BUILT.to_string())),
+        };
+        // Существующий индекс открываем сразу, новый будет создан в build_index.
+        // Пустой каталог или индекс со старой схемой не открывается: ошибку открытия
+        // вернут методы чтения, а полный build_index пересоздаст индекс
+        if flib.index_exists() {
+            let opened = flib.open_with(|path| Index::open_in_dir(path));
+            *flib
+                .index
+                .get_mut()
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))? = opened;
         }
+        Ok(flib)
     }
 
     /// Проверяет, существует ли индекс
@@ -548,35 +2096,1510 @@ impl FlibRS {
         Path::new(&self.index_path).exists()
     }
 
-    /// Построение индекса из .inpx файла
-    fn build_index(&self, inpx_path: String) -> PyResult<()> {
-        build_tantivy_index(&inpx_path, &self.index_path, &self.zip_archives_dir)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("{}", e)))
+    /// Переоткрывает индекс с диска, например после перестройки другим процессом
+    fn reload(&self) -> PyResult<()> {
+        self.reopen(|path| Index::open_in_dir(path)).map(|_| ())
     }
 
-    /// Поиск по запросу, возвращает список кортежей (id, author, title, score)
-    fn search(&self, query: String) -> PyResult<Vec<(u64, String, String, f32)>> {
-        search_tantivy(&self.index_path, &query)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("{}", e)))
+    /// Закрывает индекс и освобождает ридер; следующий вызов `reload()` откроет его снова
+    fn close(&self) -> PyResult<()> {
+        *self
+            .index
+            .write()
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))? =
+            Err("индекс закрыт, вызовите reload()".to_string());
+        Ok(())
+    }
+
+    /// Построение индекса из .inpx файла, возвращает отчёт о построении.
+    /// `bad_lines_limit` - сколько проблемных строк сохранить в отчёте целиком,
+    /// `incremental` - обновить существующий индекс вместо полной перестройки
+    #[pyo3(signature = (inpx_path, bad_lines_limit=0, incremental=false))]
+    fn build_index(
+        &self,
+        py: Python<'_>,
+        inpx_path: String,
+        bad_lines_limit: usize,
+        incremental: bool,
+    ) -> PyResult<BuildReport> {
+        let idx = match self.open_index() {
+            Ok(idx) if incremental || idx.index.schema() == create_schema() => idx,
+            _ if incremental => self.reopen(open_or_create_index)?,
+            // Полная перестройка пересоздаёт неполный индекс или индекс со старой схемой
+            _ => self.reopen(open_for_full_build)?,
+        };
+        // Тяжёлая работа идёт без GIL, чтобы не блокировать остальные потоки Python
+        py.allow_threads(|| {
+            build_tantivy_index(
+                &idx,
+                &inpx_path,
+                &self.zip_archives_dir,
+                bad_lines_limit,
+                incremental,
+            )
+            .map_err(to_py_err)
+        })
+    }
+
+    /// Поиск по запросу, возвращает страницу найденных книг с их score.
+    /// Удалённые книги (DEL=1) не возвращаются, если не указан `include_deleted`;
+    /// `limit` и `offset` задают страницу, `limit=0` - только подсчёт;
+    /// `fuzzy` находит книги и при опечатках в запросе;
+    /// `translit` ищет кириллические книги по латинскому запросу и наоборот
+    /// (запросы с операторами `+`, `-`, `AND`, полями и т.п. - без транслитерации).
+    /// Запрос с ошибкой синтаксиса ищется как обычный текст; при `strict`
+    /// вместо этого выбрасывается `QuerySyntaxError` с позицией ошибки.
+    /// `facets` добавляет в результат число найденных книг по жанрам, языкам,
+    /// форматам и десятилетиям. Фильтры: `langs` и `exts` - списки допустимых
+    /// языков и форматов, `date_from`/`date_to` - дата поступления `YYYY-MM-DD`
+    /// (включительно), `size_min`/`size_max` - размер файла, `min_rating` - LIBRATE.
+    /// `sort` - порядок выдачи: `relevance`, `date` (сначала новые), `title`, `author`,
+    /// `serno` (по порядку в серии), `size` (сначала большие), `rating`; `reverse` - обратный.
+    /// `highlight` добавляет к найденным книгам `snippets` - автора и название
+    /// с совпавшими словами, обрамлёнными `markers`. Если маркеры - теги HTML
+    /// (как `<b>` по умолчанию), текст в `marked` экранируется; с другими
+    /// маркерами он не экранируется, для своей разметки используйте `ranges`
+    #[pyo3(signature = (
+        query,
+        include_deleted=false,
+        limit=10,
+        offset=0,
+        fuzzy=false,
+        translit=true,
+        strict=false,
+        facets=false,
+        langs=None,
+        exts=None,
+        date_from=None,
+        date_to=None,
+        size_min=None,
+        size_max=None,
+        min_rating=None,
+        sort="relevance",
+        reverse=false,
+        highlight=false,
+        markers=(String::from("<b>"), String::from("</b>"))
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn search(
+        &self,
+        py: Python<'_>,
+        query: String,
+        include_deleted: bool,
+        limit: usize,
+        offset: usize,
+        fuzzy: bool,
+        translit: bool,
+        strict: bool,
+        facets: bool,
+        langs: Option<Vec<String>>,
+        exts: Option<Vec<String>>,
+        date_from: Option<String>,
+        date_to: Option<String>,
+        size_min: Option<u64>,
+        size_max: Option<u64>,
+        min_rating: Option<u64>,
+        sort: &str,
+        reverse: bool,
+        highlight: bool,
+        markers: (String, String),
+    ) -> PyResult<SearchPage> {
+        let idx = self.open_index()?;
+        let filters = SearchFilters::parse(
+            langs, exts, date_from, date_to, size_min, size_max, min_rating,
+        )
+        .map_err(PyValueError::new_err)?;
+        let sort = SortOrder::parse(sort).map_err(PyValueError::new_err)?;
+        let options = SearchOptions {
+            include_deleted,
+            fuzzy,
+            translit,
+            strict,
+            facets,
+            filters,
+            sort,
+            reverse,
+            highlight: highlight.then_some(markers),
+            limit,
+            offset,
+        };
+        py.allow_threads(|| search_tantivy(&idx, &query, &options).map_err(to_py_err))
+    }
+
+    /// Поиск по отдельным полям: все заданные поля должны совпасть, значения
+    /// ищутся как обычный текст без синтаксиса запросов. `genre` - код жанра
+    /// (`sf_fantasy`; `sf` - это отдельный жанр «Научная фантастика»), `lang` - код
+    /// языка (`ru`), `genre_group` - код группы жанров (`sf` - вся фантастика).
+    /// Без заданных полей возвращаются все книги; `facets`, фильтры, сортировка
+    /// и подсветка - как в `search`
+    #[pyo3(signature = (
+        author=None,
+        title=None,
+        series=None,
+        genre=None,
+        lang=None,
+        genre_group=None,
+        include_deleted=false,
+        limit=10,
+        offset=0,
+        fuzzy=false,
+        translit=true,
+        facets=false,
+        langs=None,
+        exts=None,
+        date_from=None,
+        date_to=None,
+        size_min=None,
+        size_max=None,
+        min_rating=None,
+        sort="relevance",
+        reverse=false,
+        highlight=false,
+        markers=(String::from("<b>"), String::from("</b>"))
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn search_by(
+        &self,
+        py: Python<'_>,
+        author: Option<String>,
+        title: Option<String>,
+        series: Option<String>,
+        genre: Option<String>,
+        lang: Option<String>,
+        genre_group: Option<String>,
+        include_deleted: bool,
+        limit: usize,
+        offset: usize,
+        fuzzy: bool,
+        translit: bool,
+        facets: bool,
+        langs: Option<Vec<String>>,
+        exts: Option<Vec<String>>,
+        date_from: Option<String>,
+        date_to: Option<String>,
+        size_min: Option<u64>,
+        size_max: Option<u64>,
+        min_rating: Option<u64>,
+        sort: &str,
+        reverse: bool,
+        highlight: bool,
+        markers: (String, String),
+    ) -> PyResult<SearchPage> {
+        let idx = self.open_index()?;
+        let filters = SearchFilters::parse(
+            langs, exts, date_from, date_to, size_min, size_max, min_rating,
+        )
+        .map_err(PyValueError::new_err)?;
+        let sort = SortOrder::parse(sort).map_err(PyValueError::new_err)?;
+        let query = FieldQuery {
+            author,
+            title,
+            series,
+            genre,
+            genre_group,
+            lang,
+        };
+        let options = SearchOptions {
+            include_deleted,
+            fuzzy,
+            translit,
+            strict: false,
+            facets,
+            filters,
+            sort,
+            reverse,
+            highlight: highlight.then_some(markers),
+            limit,
+            offset,
+        };
+        py.allow_threads(|| search_fields(&idx, &query, &options).map_err(to_py_err))
     }
 
     /// Скачивание книги по `id`
-    fn download(&self, id: u64) -> PyResult<bool> {
-        download_file(&self.index_path, id)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("{}", e)))
+    fn download(&self, py: Python<'_>, id: u64) -> PyResult<bool> {
+        let idx = self.open_index()?;
+        let dir = Path::new(&self.zip_archives_dir);
+        py.allow_threads(|| download_file(&idx, dir, id).map_err(to_py_err))
+    }
+    /// Содержимое файла книги и его формат (`fb2`, `epub`, `djvu`, ...)
+    fn get_file_bytes(&self, py: Python<'_>, id: u64) -> PyResult<(Py<PyBytes>, String)> {
+        let idx = self.open_index()?;
+        let dir = Path::new(&self.zip_archives_dir);
+        let (bytes, format) =
+            py.allow_threads(|| get_file_bytes(&idx, dir, id).map_err(to_py_err))?;
+        Ok((PyBytes::new_bound(py, &bytes).unbind(), format))
+    }
+    fn get_info(&self, py: Python<'_>, id: u64) -> PyResult<Book> {
+        let idx = self.open_index()?;
+        py.allow_threads(|| get_info(&idx, id).map_err(to_py_err))
+    }
+
+    /// Все книги серии `name` в порядке чтения (по номеру в серии).
+    /// Название сравнивается без учёта регистра и Ё
+    #[pyo3(signature = (name, include_deleted=false))]
+    fn series(&self, py: Python<'_>, name: String, include_deleted: bool) -> PyResult<Vec<Book>> {
+        let idx = self.open_index()?;
+        py.allow_threads(|| series_books(&idx, &name, include_deleted).map_err(to_py_err))
+    }
+
+    /// Поиск серий по словам названия с числом книг и авторами;
+    /// пустой запрос возвращает самые большие серии
+    #[pyo3(signature = (query, limit=10, include_deleted=false))]
+    fn series_search(
+        &self,
+        py: Python<'_>,
+        query: String,
+        limit: usize,
+        include_deleted: bool,
+    ) -> PyResult<Vec<Series>> {
+        let idx = self.open_index()?;
+        py.allow_threads(|| search_series(&idx, &query, limit, include_deleted).map_err(to_py_err))
+    }
+
+    /// Библиография автора по его ключу `Author.key` (`толстой лев николаевич`):
+    /// книги сгруппированы по сериям, книги вне серий - отдельно
+    #[pyo3(signature = (author_key, include_deleted=false))]
+    fn books_by_author(
+        &self,
+        py: Python<'_>,
+        author_key: String,
+        include_deleted: bool,
+    ) -> PyResult<Bibliography> {
+        let idx = self.open_index()?;
+        py.allow_threads(|| {
+            author_bibliography(&idx, &author_key, include_deleted).map_err(to_py_err)
+        })
     }
-    fn get_file_bytes(&self, id: u64) -> PyResult<Vec<u8>> {
-        get_file_bytes(&self.index_path, id)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("{}", e)))
+
+    /// Алфавитный каталог авторов с числом книг: авторы, чьё имя в виде
+    /// `фамилия имя отчество` начинается с `prefix` (без учёта регистра и Ё)
+    #[pyo3(signature = (prefix="", limit=50, offset=0))]
+    fn authors(
+        &self,
+        py: Python<'_>,
+        prefix: &str,
+        limit: usize,
+        offset: usize,
+    ) -> PyResult<Vec<AuthorCount>> {
+        let idx = self.open_index()?;
+        py.allow_threads(|| {
+            let catalog = idx.author_catalog().map_err(to_py_err)?;
+            Ok(catalog.page(prefix, limit, offset))
+        })
     }
-    fn get_info(&self, id: u64) -> PyResult<(String, String)> {
-        get_info(&self.index_path, id)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!("{}", e)))
+
+    /// Подсказки при вводе запроса: авторы, названия или серии, чей ключ
+    /// начинается с `prefix` (без учёта регистра и Ё), сначала самые частые.
+    /// Удалённые книги (DEL=1) не учитываются, если не указан `include_deleted`
+    #[pyo3(signature = (prefix, kind="author", limit=10, include_deleted=false))]
+    fn suggest(
+        &self,
+        py: Python<'_>,
+        prefix: &str,
+        kind: &str,
+        limit: usize,
+        include_deleted: bool,
+    ) -> PyResult<Vec<Suggestion>> {
+        let kind = SuggestKind::parse(kind).map_err(PyValueError::new_err)?;
+        let idx = self.open_index()?;
+        py.allow_threads(|| {
+            suggest::suggest(
+                &idx.reader.searcher(),
+                &idx.fields,
+                prefix,
+                kind,
+                limit,
+                include_deleted,
+            )
+            .map_err(to_py_err)
+        })
+    }
+
+    /// Дерево жанров с количеством книг для навигации по жанрам
+    fn genres(&self, py: Python<'_>) -> PyResult<Vec<GenreGroup>> {
+        let idx = self.open_index()?;
+        py.allow_threads(|| genres_tree(&idx).map_err(to_py_err))
     }
 }
 
 #[pymodule]
 fn flib_rs(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<FlibRS>()?;
+    m.add_class::<Author>()?;
+    m.add_class::<AuthorCount>()?;
+    m.add_class::<Book>()?;
+    m.add_class::<SearchHit>()?;
+    m.add_class::<Snippet>()?;
+    m.add_class::<SearchPage>()?;
+    m.add_class::<Genre>()?;
+    m.add_class::<GenreGroup>()?;
+    m.add_class::<Series>()?;
+    m.add_class::<SeriesBooks>()?;
+    m.add_class::<Suggestion>()?;
+    m.add_class::<Bibliography>()?;
+    m.add_class::<BuildReport>()?;
+    m.add(
+        "BookDeletedError",
+        m.py().get_type_bound::<BookDeletedError>(),
+    )?;
+    m.add(
+        "QuerySyntaxError",
+        m.py().get_type_bound::<QuerySyntaxError>(),
+    )?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, inp_line, TempDir};
+
+    /// Поля строки .inp, как их режет `build_tantivy_index`
+    fn split(line: &str) -> Vec<&str> {
+        line.split('\x04').collect()
+    }
+
+    #[test]
+    fn default_layout_matches_flibusta_columns() {
+        let layout = InpLayout::default();
+        assert_eq!(layout.columns["AUTHOR"], 0);
+        assert_eq!(layout.columns["FILE"], 5);
+        assert_eq!(layout.columns["KEYWORDS"], 13);
+        assert_eq!(layout.min_fields, 6);
+    }
+
+    #[test]
+    fn layout_with_custom_order_and_trailing_separator() {
+        let layout = InpLayout::parse(" file;title ; author;lang;\r\n").unwrap();
+        assert_eq!(layout.columns["FILE"], 0);
+        assert_eq!(layout.columns["TITLE"], 1);
+        assert_eq!(layout.columns["AUTHOR"], 2);
+        assert_eq!(layout.columns["LANG"], 3);
+        assert_eq!(layout.columns.len(), 4);
+        assert_eq!(layout.min_fields, 3);
+    }
+
+    #[test]
+    fn layout_without_required_column_is_rejected() {
+        let err = InpLayout::parse("AUTHOR;GENRE;TITLE;SERIES").unwrap_err();
+        assert!(err.contains("FILE"), "{}", err);
+        assert!(InpLayout::parse("").is_err());
+    }
+
+    #[test]
+    fn layout_skips_empty_column_names() {
+        let layout = InpLayout::parse("AUTHOR;;TITLE;FILE").unwrap();
+        assert_eq!(layout.columns["TITLE"], 2);
+        assert_eq!(layout.min_fields, 4);
+    }
+
+    #[test]
+    fn parse_book_with_default_layout() {
+        let line = "Толстой,Лев,Николаевич:\x04Prose_Classic: SF :\x04Война и мир\x04\x04\
+                    \x04101\x04100000\x0455\x040\x04FB2\x042007-12-05\x04RU\x045\x04война\x04";
+        let book = parse_book(&InpLayout::default(), &split(line), "a.zip".to_string()).unwrap();
+        assert_eq!(book.id, 101);
+        assert_eq!(book.authors.len(), 1);
+        assert_eq!(book.authors[0].name, "Лев Николаевич Толстой");
+        assert_eq!(book.book_title, "Война и мир");
+        assert_eq!(book.genres, vec!["prose_classic", "sf"]);
+        assert_eq!(book.series, "");
+        assert_eq!(book.serno, 0);
+        assert_eq!(book.size, 100000);
+        assert_eq!(book.libid, 55);
+        assert!(!book.deleted);
+        assert_eq!(book.ext, "fb2");
+        assert_eq!(book.date, "2007-12-05");
+        assert_eq!(book.lang, "ru");
+        assert_eq!(book.rating, 5);
+        assert_eq!(book.keywords, "война");
+        assert_eq!(book.zip_archive, "a.zip");
+    }
+
+    #[test]
+    fn parse_book_with_custom_layout() {
+        let layout = InpLayout::parse("FILE;TITLE;AUTHOR;DEL;").unwrap();
+        let line = "7\x04 Ведьмак \x04Сапковский,Анджей,:\x041\x04";
+        let book = parse_book(&layout, &split(line), String::new()).unwrap();
+        assert_eq!(book.id, 7);
+        assert_eq!(book.book_title, "Ведьмак");
+        assert_eq!(book.authors[0].name, "Анджей Сапковский");
+        assert!(book.deleted);
+        // Колонок, которых нет в раскладке, в книге просто нет
+        assert_eq!(book.lang, "");
+        assert_eq!(book.size, 0);
+        assert!(book.genres.is_empty());
+    }
+
+    #[test]
+    fn author_entry_is_split_into_name_parts() {
+        let author = Author::parse(" Толстой , Лев ,Николаевич ").unwrap();
+        assert_eq!(author.last_name, "Толстой");
+        assert_eq!(author.first_name, "Лев");
+        assert_eq!(author.middle_name, "Николаевич");
+        assert_eq!(author.name, "Лев Николаевич Толстой");
+        assert_eq!(author.sort_name(), "Толстой Лев Николаевич");
+        assert_eq!(author.key, "толстой лев николаевич");
+        assert_eq!(author.raw(), "Толстой,Лев,Николаевич");
+    }
+
+    #[test]
+    fn author_with_missing_parts() {
+        let author = Author::parse("Сапковский,Анджей,").unwrap();
+        assert_eq!(author.name, "Анджей Сапковский");
+        assert_eq!(author.middle_name, "");
+        let author = Author::parse("Гомер").unwrap();
+        assert_eq!(author.name, "Гомер");
+        assert_eq!(author.first_name, "");
+        // Запятые в отчестве не режутся: в записи не больше трёх частей
+        let author = Author::parse("Толкин,Джон,Рональд,Руэл").unwrap();
+        assert_eq!(author.middle_name, "Рональд,Руэл");
+    }
+
+    #[test]
+    fn author_key_folds_case_and_yo() {
+        let author = Author::parse("Ёжиков,Пётр,").unwrap();
+        assert_eq!(author.key, "ежиков петр");
+        assert_eq!(author.name, "Пётр Ёжиков");
+    }
+
+    #[test]
+    fn empty_author_entries_are_skipped() {
+        assert!(Author::parse("").is_none());
+        assert!(Author::parse(",,").is_none());
+        assert!(Author::parse(" , , ").is_none());
+        let authors = Author::parse_list("Лукьяненко,Сергей,Васильевич::,,:Васильев,Владимир,:");
+        let names: Vec<&str> = authors.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["Сергей Васильевич Лукьяненко", "Владимир Васильев"]);
+        assert!(Author::parse_list("").is_empty());
+    }
+
+    #[test]
+    fn parse_book_rejects_non_numeric_file() {
+        let layout = InpLayout::parse("FILE;TITLE;AUTHOR").unwrap();
+        let err = parse_book(
+            &layout,
+            &split("abc\x04Ёлка\x04Ёжиков,Пётр,"),
+            String::new(),
+        )
+        .unwrap_err();
+        assert!(err.contains("abc"), "{}", err);
+        assert!(parse_book(&layout, &split("\x04Ёлка\x04"), String::new()).is_err());
+    }
+
+    /// Живые документы с `id`
+    fn docs_with_id(idx: &OpenIndex, id: u64) -> usize {
+        let query = TermQuery::new(
+            Term::from_field_u64(idx.fields.id, id),
+            IndexRecordOption::Basic,
+        );
+        idx.reader.searcher().search(&query, &Count).unwrap()
+    }
+
+    #[test]
+    fn incremental_build_adds_updates_and_removes_books() {
+        let dir = TempDir::new("incremental");
+        let idx = testing::open_index(&dir);
+        let report = testing::build(
+            &dir,
+            &idx,
+            &[
+                inp_line(1, "Толстой,Лев,Николаевич:", "Война и мир", "", 0),
+                inp_line(2, "Булгаков,Михаил,:", "Мастер и Маргарита", "", 0),
+                inp_line(3, "Гоголь,Николай,:", "Мёртвые души", "", 0),
+            ],
+            false,
+        );
+        assert_eq!(report.indexed, 3);
+
+        let report = testing::build(
+            &dir,
+            &idx,
+            &[
+                inp_line(1, "Толстой,Лев,Николаевич:", "Война и мир", "", 0),
+                inp_line(2, "Булгаков,Михаил,:", "Белая гвардия", "", 0),
+                inp_line(4, "Чехов,Антон,Павлович:", "Палата №6", "", 0),
+            ],
+            true,
+        );
+        assert_eq!(
+            (
+                report.added,
+                report.updated,
+                report.removed,
+                report.unchanged
+            ),
+            (1, 1, 1, 1)
+        );
+        assert_eq!(report.indexed, 3);
+
+        idx.reader.reload().unwrap();
+        assert_eq!(idx.reader.searcher().search(&AllQuery, &Count).unwrap(), 3);
+        // Изменённая книга заменена, а не продублирована
+        assert_eq!(docs_with_id(&idx, 2), 1);
+        assert_eq!(docs_with_id(&idx, 3), 0);
+        assert_eq!(docs_with_id(&idx, 4), 1);
+        let book = idx.fields.read_book(&find_book_doc(&idx, 2).unwrap());
+        assert_eq!(book.book_title, "Белая гвардия");
+    }
+
+    #[test]
+    fn incremental_build_ignores_archive_dir_spelling() {
+        let dir = TempDir::new("archive_spelling");
+        let idx = testing::open_index(&dir);
+        let lines = [inp_line(1, "Толстой,Лев,Николаевич:", "Война и мир", "", 0)];
+        let inpx = dir.path().join("lib.inpx");
+        testing::write_inpx(&inpx, &lines);
+        build_tantivy_index(&idx, &inpx, &dir.archives(), 0, false).unwrap();
+
+        let dotted = dir.path().join(".").join("archive");
+        let report = build_tantivy_index(&idx, &inpx, &dotted, 0, true).unwrap();
+        assert_eq!((report.unchanged, report.updated), (1, 0));
+    }
+
+    #[test]
+    fn missing_archive_dir_keeps_indexed_books() {
+        let dir = TempDir::new("missing_archive_dir");
+        let idx = testing::open_index(&dir);
+        let lines = [
+            inp_line(1, "Толстой,Лев,Николаевич:", "Война и мир", "", 0),
+            inp_line(2, "Булгаков,Михаил,:", "Мастер и Маргарита", "", 0),
+        ];
+        let inpx = dir.path().join("lib.inpx");
+        testing::write_inpx(&inpx, &lines);
+        build_tantivy_index(&idx, &inpx, &dir.archives(), 0, false).unwrap();
+
+        let wrong_dir = dir.path().join("unmounted");
+        for incremental in [true, false] {
+            let report = build_tantivy_index(&idx, &inpx, &wrong_dir, 0, incremental).unwrap();
+            assert_eq!((report.removed, report.kept), (0, 2));
+            assert_eq!(report.missing_archive, 2);
+            assert_eq!(idx.reader.searcher().search(&AllQuery, &Count).unwrap(), 2);
+            let catalog = idx.author_catalog().unwrap();
+            assert_eq!(catalog.page("", 10, 0).len(), 2);
+        }
+
+        // Книга, исчезнувшая из INPX, удаляется и при недоступном архиве
+        testing::write_inpx(&inpx, &lines[..1]);
+        let report = build_tantivy_index(&idx, &inpx, &wrong_dir, 0, true).unwrap();
+        assert_eq!((report.removed, report.kept), (1, 1));
+        assert_eq!(docs_with_id(&idx, 1), 1);
+        assert_eq!(docs_with_id(&idx, 2), 0);
+    }
+
+    #[test]
+    fn archives_are_resolved_against_current_dir() {
+        let lines = [inp_line(1, "Гоголь,Николай,:", "Нос", "", 0)];
+        let (dir, idx) = testing::index_with("moved_archives", &lines);
+        assert_eq!(get_info(&idx, 1).unwrap().zip_archive, testing::ARCHIVE);
+
+        // Библиотеку перенесли: индекс тот же, архивы в другом каталоге
+        let moved = dir.path().join("moved");
+        fs::rename(dir.archives(), &moved).unwrap();
+        testing::write_archive(
+            &moved.join(testing::ARCHIVE),
+            &[("1.fb2", b"<FictionBook/>")],
+        );
+        let report =
+            build_tantivy_index(&idx, &dir.path().join("lib.inpx"), &moved, 0, true).unwrap();
+        assert_eq!(report.unchanged, 1);
+
+        let (bytes, format) = get_file_bytes(&idx, &moved, 1).unwrap();
+        assert_eq!(
+            (bytes.as_slice(), format.as_str()),
+            (&b"<FictionBook/>"[..], "fb2")
+        );
+        let err = get_file_bytes(&idx, &dir.archives(), 1).unwrap_err();
+        assert!(err.to_string().contains("не существует"), "{}", err);
+    }
+
+    /// Схема прежней версии: те же поля, но `keywords` не хранится
+    fn outdated_schema() -> Schema {
+        let mut builder = Schema::builder();
+        for (_, entry) in create_schema().fields() {
+            if entry.name() == "keywords" {
+                builder.add_text_field("keywords", TEXT);
+            } else {
+                builder.add_field(entry.clone());
+            }
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn full_build_recreates_empty_or_outdated_index() {
+        let dir = TempDir::new("outdated");
+        let path = dir.path().join("idx");
+        let path = path.to_str().unwrap();
+
+        // Пустой каталог - ещё не индекс
+        fs::create_dir_all(path).unwrap();
+        assert!(Index::open_in_dir(path).is_err());
+        assert_eq!(open_for_full_build(path).unwrap().schema(), create_schema());
+
+        // Индекс без части полей не открывается, но пересоздаётся при сборке
+        fs::remove_dir_all(path).unwrap();
+        fs::create_dir_all(path).unwrap();
+        let mut old = Schema::builder();
+        old.add_u64_field("id", INDEXED | STORED);
+        Index::create_in_dir(path, old.build()).unwrap();
+        let index = Index::open_in_dir(path).unwrap();
+        assert!(OpenIndex::new(path, index, ReloadPolicy::Manual).is_err());
+
+        let idx = OpenIndex::new(
+            path,
+            open_for_full_build(path).unwrap(),
+            ReloadPolicy::Manual,
+        )
+        .unwrap();
+        let lines = [inp_line(1, "Толстой,Лев,Николаевич:", "Война и мир", "", 0)];
+        assert_eq!(testing::build(&dir, &idx, &lines, false).indexed, 1);
+        assert_eq!(open_for_full_build(path).unwrap().schema(), create_schema());
+    }
+
+    #[test]
+    fn full_build_keeps_foreign_files() {
+        let dir = TempDir::new("foreign_files");
+        let path = dir.path().join("idx");
+        let path_str = path.to_str().unwrap();
+
+        // Каталог с чужими файлами - не индекс, его не трогаем
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("precious.txt"), b"data").unwrap();
+        assert!(open_for_full_build(path_str).is_err());
+        assert!(path.join("precious.txt").exists());
+
+        // В индексе со старой схемой удаляются только файлы индекса
+        fs::remove_file(path.join("precious.txt")).unwrap();
+        Index::create_in_dir(path_str, outdated_schema()).unwrap();
+        let idx = testing::open_index(&dir);
+        let lines = [inp_line(1, "Гоголь,Николай,:", "Нос", "", 0)];
+        testing::build(&dir, &idx, &lines, false);
+        drop(idx);
+        fs::write(path.join("precious.txt"), b"data").unwrap();
+
+        let index = open_for_full_build(path_str).unwrap();
+        assert_eq!(index.schema(), create_schema());
+        assert!(path.join("precious.txt").exists());
+        assert!(!path.join(catalog::CATALOG_FILE).exists());
+        let segments = fs::read_dir(&path)
+            .unwrap()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".store"))
+            .count();
+        assert_eq!(segments, 0);
+    }
+
+    #[test]
+    fn open_error_names_the_cause() {
+        let dir = TempDir::new("corrupt_meta");
+        let path = dir.path().join("idx");
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("meta.json"), b"{not json").unwrap();
+
+        let flib = FlibRS {
+            index_path: path.to_string_lossy().to_string(),
+            zip_archives_dir: String::new(),
+            reload_policy: ReloadPolicy::Manual,
+            index: RwLock::new(Err(NOT_BUILT.to_string())),
+        };
+        let reason = flib
+            .open_with(|path| Index::open_in_dir(path))
+            .err()
+            .unwrap();
+        assert!(
+            reason.starts_with("Не удалось открыть индекс"),
+            "{}",
+            reason
+        );
+        assert!(reason.contains("meta.json"), "{}", reason);
+    }
+
+    #[test]
+    fn incremental_build_rejects_outdated_schema() {
+        let dir = TempDir::new("outdated_incremental");
+        let path = dir.path().join("idx");
+        let path = path.to_str().unwrap();
+        fs::create_dir_all(path).unwrap();
+        Index::create_in_dir(path, outdated_schema()).unwrap();
+
+        let idx = OpenIndex::new(
+            path,
+            open_or_create_index(path).unwrap(),
+            ReloadPolicy::Manual,
+        )
+        .unwrap();
+        let inpx = dir.path().join("lib.inpx");
+        testing::write_inpx(&inpx, &[inp_line(1, "Гоголь,Николай,:", "Нос", "", 0)]);
+        assert!(build_tantivy_index(&idx, &inpx, &dir.archives(), 0, true).is_err());
+
+        let index = open_for_full_build(path).unwrap();
+        assert_eq!(index.schema(), create_schema());
+    }
+
+    /// `id` книг на странице поиска с сортировкой `sort`
+    fn sorted_ids(
+        idx: &OpenIndex,
+        query: &str,
+        sort: SortOrder,
+        reverse: bool,
+        offset: usize,
+        limit: usize,
+    ) -> Vec<u64> {
+        let options = SearchOptions {
+            sort,
+            reverse,
+            offset,
+            limit,
+            ..SearchOptions::default()
+        };
+        search_tantivy(idx, query, &options)
+            .unwrap()
+            .hits
+            .iter()
+            .map(|hit| hit.book.id)
+            .collect()
+    }
+
+    #[test]
+    fn title_and_author_sort_compare_whole_strings() {
+        // Первые 9 букв совпадают: «Стругацки», «Приключен»
+        let lines = [
+            inp_line(
+                1,
+                "Стругацкий,Борис,Натанович:",
+                "Поиск предназначения",
+                "",
+                0,
+            ),
+            inp_line(
+                2,
+                "Стругацкий,Аркадий,Натанович:",
+                "Экспедиция в преисподнюю",
+                "",
+                0,
+            ),
+            inp_line(3, "Твен,Марк,:", "Приключения Тома Сойера", "", 0),
+            inp_line(4, "Твен,Марк,:", "Приключения Гекльберри Финна", "", 0),
+        ];
+        let (_dir, idx) = testing::index_with("collated_sort", &lines);
+
+        assert_eq!(
+            sorted_ids(&idx, "стругацкий", SortOrder::Author, false, 0, 10),
+            [2, 1]
+        );
+        assert_eq!(
+            sorted_ids(&idx, "стругацкий", SortOrder::Author, true, 0, 10),
+            [1, 2]
+        );
+        assert_eq!(
+            sorted_ids(&idx, "приключения", SortOrder::Title, false, 0, 10),
+            [4, 3]
+        );
+        assert_eq!(
+            sorted_ids(&idx, "приключения", SortOrder::Title, true, 0, 10),
+            [3, 4]
+        );
+
+        // Книги с тем же ключом за пределами страницы тоже участвуют в сравнении
+        assert_eq!(
+            sorted_ids(&idx, "стругацкий", SortOrder::Author, false, 0, 1),
+            [2]
+        );
+        assert_eq!(
+            sorted_ids(&idx, "стругацкий", SortOrder::Author, false, 1, 1),
+            [1]
+        );
+        assert_eq!(
+            sorted_ids(&idx, "приключения", SortOrder::Title, false, 0, 1),
+            [4]
+        );
+    }
+
+    #[test]
+    fn huge_limit_and_offset_are_clamped() {
+        let lines = [
+            inp_line(1, "Гоголь,Николай,:", "Нос", "", 0),
+            inp_line(2, "Гоголь,Николай,:", "Шинель", "", 0),
+        ];
+        let (_dir, idx) = testing::index_with("huge_page", &lines);
+
+        for sort in [SortOrder::Relevance, SortOrder::Title] {
+            let ids = sorted_ids(&idx, "гоголь", sort, false, 0, 1 << 45);
+            assert_eq!(ids.len(), 2);
+            assert_eq!(
+                sorted_ids(&idx, "гоголь", sort, false, 1, usize::MAX).len(),
+                1
+            );
+            assert!(sorted_ids(&idx, "гоголь", sort, false, usize::MAX, usize::MAX).is_empty());
+        }
+        let options = SearchOptions {
+            offset: usize::MAX,
+            limit: 1 << 45,
+            ..SearchOptions::default()
+        };
+        let page = search_tantivy(&idx, "гоголь", &options).unwrap();
+        assert_eq!((page.total, page.hits.len()), (2, 0));
+        assert!(!page.has_more());
+    }
+
+    /// `id` книг по запросу `гоголь` с фильтрами
+    fn filtered_ids(idx: &OpenIndex, filters: SearchFilters) -> Vec<u64> {
+        let options = SearchOptions {
+            filters,
+            sort: SortOrder::Date,
+            reverse: true,
+            ..SearchOptions::default()
+        };
+        search_tantivy(idx, "гоголь", &options)
+            .unwrap()
+            .hits
+            .iter()
+            .map(|hit| hit.book.id)
+            .collect()
+    }
+
+    #[test]
+    fn filters_by_lang_ext_date_size_and_rating() {
+        let book = |id, size: &str, date: &str, lang: &str, ext: &str, rating: &str| {
+            let line = inp_line(id, "Гоголь,Николай,:", "Повесть", "", 0);
+            let line = testing::with_column(&line, "SIZE", size);
+            let line = testing::with_column(&line, "DATE", date);
+            let line = testing::with_column(&line, "LANG", lang);
+            let line = testing::with_column(&line, "EXT", ext);
+            testing::with_column(&line, "LIBRATE", rating)
+        };
+        let lines = [
+            book(1, "1000", "2020-01-01", "ru", "fb2", ""),
+            book(2, "5000", "2021-06-15", "EN", "EPUB", "4"),
+            book(3, "3000", "2021-06-16", "ru", "pdf", "5"),
+        ];
+        let (_dir, idx) = testing::index_with("filters", &lines);
+        let filters = |langs: &[&str],
+                       exts: &[&str],
+                       date_from: Option<&str>,
+                       date_to: Option<&str>,
+                       size: (Option<u64>, Option<u64>),
+                       min_rating: Option<u64>| {
+            let list = |values: &[&str]| {
+                (!values.is_empty()).then(|| values.iter().map(|v| v.to_string()).collect())
+            };
+            SearchFilters::parse(
+                list(langs),
+                list(exts),
+                date_from.map(str::to_string),
+                date_to.map(str::to_string),
+                size.0,
+                size.1,
+                min_rating,
+            )
+            .unwrap()
+        };
+        let none = (None, None);
+
+        assert_eq!(
+            filtered_ids(&idx, filters(&[], &[], None, None, none, None)),
+            [1, 2, 3]
+        );
+        // Даты включительно с обеих сторон
+        let ids = filtered_ids(
+            &idx,
+            filters(&[], &[], None, Some("2021-06-15"), none, None),
+        );
+        assert_eq!(ids, [1, 2]);
+        let ids = filtered_ids(
+            &idx,
+            filters(&[], &[], Some("2021-06-15"), None, none, None),
+        );
+        assert_eq!(ids, [2, 3]);
+        // Границы размера тоже включительно
+        let ids = filtered_ids(
+            &idx,
+            filters(&[], &[], None, None, (Some(3000), None), None),
+        );
+        assert_eq!(ids, [2, 3]);
+        let ids = filtered_ids(
+            &idx,
+            filters(&[], &[], None, None, (None, Some(3000)), None),
+        );
+        assert_eq!(ids, [1, 3]);
+        let size = (Some(3000), Some(3000));
+        assert_eq!(
+            filtered_ids(&idx, filters(&[], &[], None, None, size, None)),
+            [3]
+        );
+        // Языки и форматы без учёта регистра и пробелов
+        assert_eq!(
+            filtered_ids(&idx, filters(&["en"], &[], None, None, none, None)),
+            [2]
+        );
+        assert_eq!(
+            filtered_ids(&idx, filters(&[" RU"], &[], None, None, none, None)),
+            [1, 3]
+        );
+        let ids = filtered_ids(&idx, filters(&[], &["Epub", "PDF"], None, None, none, None));
+        assert_eq!(ids, [2, 3]);
+        assert!(filtered_ids(&idx, filters(&["de"], &[], None, None, none, None)).is_empty());
+        // Рейтинг не ниже заданного
+        assert_eq!(
+            filtered_ids(&idx, filters(&[], &[], None, None, none, Some(4))),
+            [2, 3]
+        );
+        assert_eq!(
+            filtered_ids(&idx, filters(&[], &[], None, None, none, Some(5))),
+            [3]
+        );
+        // Фильтры складываются
+        let ids = filtered_ids(&idx, filters(&["ru"], &[], None, None, none, Some(1)));
+        assert_eq!(ids, [3]);
+
+        let err = SearchFilters::parse(
+            None,
+            None,
+            Some("2021-13-01".into()),
+            None,
+            None,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.contains("date_from"), "{}", err);
+    }
+
+    #[test]
+    fn genres_tree_follows_catalogue() {
+        let book = |id, genre: &str| {
+            testing::with_column(
+                &inp_line(id, "Гоголь,Николай,:", "Повесть", "", 0),
+                "GENRE",
+                genre,
+            )
+        };
+        let lines = [
+            book(1, "sf_fantasy:"),
+            book(2, "sf_history:sf_fantasy:"),
+            book(3, "prose_classic:"),
+            book(4, "my_genre:"),
+            book(5, "comics:"),
+            // Удалённая книга - единственная в группе «Детективы»
+            testing::with_column(&book(6, "det_classic:"), "DEL", "1"),
+        ];
+        let (_dir, idx) = testing::index_with("genres_tree", &lines);
+        let tree = genres_tree(&idx).unwrap();
+
+        // Группы и жанры в порядке справочника, пустые группы пропущены
+        let groups: Vec<(&str, u64)> = tree.iter().map(|g| (g.code.as_str(), g.count)).collect();
+        assert_eq!(groups, [("sf", 2), ("prose", 1), ("other", 2)]);
+        let sf: Vec<(&str, u64)> = tree[0]
+            .genres
+            .iter()
+            .map(|g| (g.code.as_str(), g.count))
+            .collect();
+        assert_eq!(sf, [("sf_history", 1), ("sf_fantasy", 2)]);
+        assert_eq!(tree[0].name_ru, "Фантастика");
+        assert_eq!(tree[0].genres[1].name_en, "Fantasy");
+
+        // Неизвестный код - в `other` после кодов справочника, с кодом вместо названия
+        let other: Vec<(&str, &str, &str)> = tree[2]
+            .genres
+            .iter()
+            .map(|g| (g.code.as_str(), g.name_ru.as_str(), g.name_en.as_str()))
+            .collect();
+        assert_eq!(
+            other,
+            [
+                ("comics", "Комиксы", "Comics"),
+                ("my_genre", "my_genre", "my_genre")
+            ]
+        );
+    }
+
+    #[test]
+    fn facets_count_live_filtered_books() {
+        let book = |id, genre: &str, lang: &str, ext: &str, date: &str| {
+            let line = inp_line(id, "Гоголь,Николай,:", "Повесть", "", 0);
+            let line = testing::with_column(&line, "GENRE", genre);
+            let line = testing::with_column(&line, "LANG", lang);
+            let line = testing::with_column(&line, "EXT", ext);
+            testing::with_column(&line, "DATE", date)
+        };
+        let lines = [
+            book(1, "sf_fantasy:sf_space:", "ru", "fb2", "1995-05-01"),
+            book(2, "prose_classic:", "en", "epub", "2003-01-01"),
+            // Без даты: в десятилетиях не считается
+            book(3, "sf_fantasy:", "ru", "fb2", ""),
+            book(4, "unknown_code:", "ru", "pdf", "2009-12-31"),
+            testing::with_column(
+                &book(5, "sf_fantasy:", "de", "fb2", "2010-01-01"),
+                "DEL",
+                "1",
+            ),
+        ];
+        let (_dir, idx) = testing::index_with("facets", &lines);
+        let facets = |include_deleted, langs: &[&str]| {
+            let options = SearchOptions {
+                include_deleted,
+                facets: true,
+                limit: 0,
+                filters: SearchFilters {
+                    langs: langs.iter().map(|l| l.to_string()).collect(),
+                    ..SearchFilters::default()
+                },
+                ..SearchOptions::default()
+            };
+            search_tantivy(&idx, "гоголь", &options).unwrap().facets
+        };
+        let counts = |pairs: &[(&str, u64)]| -> HashMap<String, u64> {
+            pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+        };
+        let expected = |genre, genre_group, lang, ext, decade| {
+            HashMap::from([
+                ("genre".to_string(), counts(genre)),
+                ("genre_group".to_string(), counts(genre_group)),
+                ("lang".to_string(), counts(lang)),
+                ("ext".to_string(), counts(ext)),
+                ("decade".to_string(), counts(decade)),
+            ])
+        };
+
+        // Книга с двумя жанрами группы считается в группе один раз,
+        // неизвестный код - в группе `other`; удалённая книга не считается
+        assert_eq!(
+            facets(false, &[]),
+            expected(
+                &[
+                    ("sf_fantasy", 2),
+                    ("sf_space", 1),
+                    ("prose_classic", 1),
+                    ("unknown_code", 1)
+                ],
+                &[("sf", 2), ("prose", 1), ("other", 1)],
+                &[("ru", 3), ("en", 1)],
+                &[("fb2", 2), ("epub", 1), ("pdf", 1)],
+                &[("1990", 1), ("2000", 2)],
+            )
+        );
+        assert_eq!(
+            facets(true, &[]),
+            expected(
+                &[
+                    ("sf_fantasy", 3),
+                    ("sf_space", 1),
+                    ("prose_classic", 1),
+                    ("unknown_code", 1)
+                ],
+                &[("sf", 3), ("prose", 1), ("other", 1)],
+                &[("ru", 3), ("en", 1), ("de", 1)],
+                &[("fb2", 3), ("epub", 1), ("pdf", 1)],
+                &[("1990", 1), ("2000", 2), ("2010", 1)],
+            )
+        );
+        // Фасеты считаются по отфильтрованной выдаче
+        assert_eq!(
+            facets(false, &["ru"]),
+            expected(
+                &[("sf_fantasy", 2), ("sf_space", 1), ("unknown_code", 1)],
+                &[("sf", 2), ("other", 1)],
+                &[("ru", 3)],
+                &[("fb2", 2), ("pdf", 1)],
+                &[("1990", 1), ("2000", 1)],
+            )
+        );
+    }
+
+    #[test]
+    fn search_by_matches_every_given_field() {
+        let book = |id, author: &str, title: &str, genre: &str, lang: &str| {
+            let line = inp_line(id, author, title, "", 0);
+            let line = testing::with_column(&line, "GENRE", genre);
+            testing::with_column(&line, "LANG", lang)
+        };
+        let lines = [
+            book(
+                1,
+                "Булгаков,Михаил,:",
+                "Мастер и Маргарита",
+                "sf_fantasy:",
+                "ru",
+            ),
+            book(
+                2,
+                "Булгаков,Михаил,:",
+                "Белая гвардия",
+                "prose_classic:",
+                "ru",
+            ),
+            book(3, "Стругацкий,Аркадий,:", "Пикник на обочине", "sf:", "RU"),
+            book(4, "Азимов,Айзек,:", "Я, робот", "sf_space:", "en"),
+            book(5, "Бродский,Иосиф,:", "Ночь: Стихи", "poetry:", "ru"),
+        ];
+        let (_dir, idx) = testing::index_with("search_by", &lines);
+        let ids = |query: FieldQuery| {
+            let mut ids: Vec<u64> = search_fields(&idx, &query, &SearchOptions::default())
+                .unwrap()
+                .hits
+                .iter()
+                .map(|hit| hit.book.id)
+                .collect();
+            ids.sort_unstable();
+            ids
+        };
+        let text = |value: &str| Some(value.to_string());
+
+        // Все заданные поля должны совпасть
+        let query = FieldQuery {
+            author: text("булгаков"),
+            title: text("мастер"),
+            ..FieldQuery::default()
+        };
+        assert_eq!(ids(query), [1]);
+        let query = FieldQuery {
+            author: text("Булгаков"),
+            title: text("пикник"),
+            ..FieldQuery::default()
+        };
+        assert!(ids(query).is_empty());
+
+        // `genre=sf` - только сам жанр «Научная фантастика», `genre_group=sf` - вся группа
+        let query = FieldQuery {
+            genre: text("sf"),
+            ..FieldQuery::default()
+        };
+        assert_eq!(ids(query), [3]);
+        let query = FieldQuery {
+            genre_group: text("SF"),
+            ..FieldQuery::default()
+        };
+        assert_eq!(ids(query), [1, 3, 4]);
+
+        let query = FieldQuery {
+            lang: text(" EN "),
+            ..FieldQuery::default()
+        };
+        assert_eq!(ids(query), [4]);
+        let query = FieldQuery {
+            lang: text("Ru"),
+            ..FieldQuery::default()
+        };
+        assert_eq!(ids(query), [1, 2, 3, 5]);
+
+        // Значения не разбираются как синтаксис запроса
+        let query = FieldQuery {
+            title: text("Ночь: Стихи"),
+            ..FieldQuery::default()
+        };
+        assert_eq!(ids(query), [5]);
+        let query = FieldQuery {
+            author: text("bulgakov"),
+            ..FieldQuery::default()
+        };
+        assert_eq!(ids(query), [1, 2]);
+
+        assert_eq!(ids(FieldQuery::default()), [1, 2, 3, 4, 5]);
+    }
+
+    /// Книги нескольких серий: «Дозоры» с соавтором, книгой без номера и удалённой книгой
+    fn series_library(name: &str) -> (TempDir, OpenIndex) {
+        let author = "Лукьяненко,Сергей,Васильевич:";
+        let lines = [
+            inp_line(3, author, "Новый дозор", "Дозоры", 0),
+            inp_line(
+                2,
+                "Лукьяненко,Сергей,Васильевич:Васильев,Владимир,:",
+                "Дневной дозор",
+                "дозоры",
+                2,
+            ),
+            inp_line(4, author, "Шестой дозор", "ДОЗОРЫ", 6),
+            inp_line(1, author, "Ночной дозор", "Дозоры", 1),
+            testing::with_column(&inp_line(5, author, "Черновик", "Дозоры", 5), "DEL", "1"),
+            inp_line(6, "Иванов,Иван,:", "Ёлочка", "Ёлки", 1),
+            inp_line(7, author, "Лабиринт отражений", "Лабиринт", 1),
+            inp_line(8, author, "Фальшивые зеркала", "Лабиринт", 2),
+            inp_line(9, author, "Геном", "Геном", 1),
+        ];
+        testing::index_with(name, &lines)
+    }
+
+    #[test]
+    fn series_books_in_reading_order() {
+        let (_dir, idx) = series_library("series_books");
+        let ids = |name: &str, include_deleted| {
+            series_books(&idx, name, include_deleted)
+                .unwrap()
+                .iter()
+                .map(|b| b.id)
+                .collect::<Vec<_>>()
+        };
+        // По номеру в серии, книга без номера - в конце
+        assert_eq!(ids("Дозоры", false), [1, 2, 4, 3]);
+        assert_eq!(ids("дОзОрЫ", false), [1, 2, 4, 3]);
+        assert_eq!(ids("Дозоры", true), [1, 2, 5, 4, 3]);
+        // Без учёта Ё
+        assert_eq!(ids("ЕЛКИ", false), [6]);
+        assert!(ids("Дозор", false).is_empty());
+    }
+
+    #[test]
+    fn series_search_counts_books_and_authors() {
+        let (_dir, idx) = series_library("series_search");
+        let found = |query: &str, limit, include_deleted| {
+            search_series(&idx, query, limit, include_deleted)
+                .unwrap()
+                .into_iter()
+                .map(|s| (s.name, s.count, s.authors))
+                .collect::<Vec<_>>()
+        };
+        let authors = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let dozory = authors(&["Сергей Васильевич Лукьяненко", "Владимир Васильев"]);
+
+        // «дозор» находит «Дозоры» благодаря стеммингу, название - из первой книги
+        assert_eq!(
+            found("дозор", 10, false),
+            [("Дозоры".to_string(), 4, dozory.clone())]
+        );
+        assert_eq!(found("дозор", 10, true)[0].1, 5);
+        assert!(found("зеркало", 10, false).is_empty());
+
+        // Пустой запрос - самые большие серии, равные по размеру - по ключу
+        let names: Vec<(String, u64)> = found("", 10, false)
+            .into_iter()
+            .map(|(name, count, _)| (name, count))
+            .collect();
+        let expected = [("Дозоры", 4), ("Лабиринт", 2), ("Геном", 1), ("Ёлки", 1)];
+        let expected: Vec<(String, u64)> =
+            expected.iter().map(|(n, c)| (n.to_string(), *c)).collect();
+        assert_eq!(names, expected);
+        let limited = found("", 2, false);
+        assert_eq!(limited.len(), 2);
+        assert_eq!(
+            limited[1],
+            (
+                "Лабиринт".to_string(),
+                2,
+                authors(&["Сергей Васильевич Лукьяненко"])
+            )
+        );
+    }
+
+    #[test]
+    fn bibliography_groups_series_and_orders_books() {
+        let author = "Лукьяненко,Сергей,Васильевич:";
+        let lines = [
+            inp_line(2, author, "Дневной дозор", "дозоры", 2),
+            inp_line(4, author, "Новый дозор", "ДОЗОРЫ", 0),
+            inp_line(1, author, "Ночной дозор", "Дозоры", 1),
+            inp_line(3, author, "Сумеречный дозор", "Дозоры", 3),
+            inp_line(5, author, "Лабиринт отражений", "Лабиринт", 1),
+            // Первые 9 букв названий совпадают
+            inp_line(6, author, "Лорд с планеты Земля", "", 0),
+            inp_line(7, author, "Лорд с планеты Альфа", "", 0),
+            testing::with_column(&inp_line(8, author, "Черновик", "", 0), "DEL", "1"),
+            inp_line(9, "Васильев,Владимир,:", "Лик чёрной Пальмиры", "Дозоры", 4),
+        ];
+        let (_dir, idx) = testing::index_with("bibliography", &lines);
+
+        let ids = |books: &[Book]| books.iter().map(|b| b.id).collect::<Vec<_>>();
+        for _ in 0..3 {
+            let bib = author_bibliography(&idx, "Лукьяненко Сергей Васильевич", false).unwrap();
+            assert_eq!(bib.author.unwrap().name, "Сергей Васильевич Лукьяненко");
+            let series: Vec<(&str, Vec<u64>)> = bib
+                .series
+                .iter()
+                .map(|s| (s.name.as_str(), ids(&s.books)))
+                .collect();
+            // Написания серии объединены, название - из первой книги, без номера - в конце
+            assert_eq!(
+                series,
+                [("Дозоры", vec![1, 2, 3, 4]), ("Лабиринт", vec![5])]
+            );
+            assert_eq!(ids(&bib.standalone), [7, 6]);
+        }
+
+        let bib = author_bibliography(&idx, "лукьяненко сергей васильевич", true).unwrap();
+        assert_eq!(ids(&bib.standalone), [7, 6, 8]);
+
+        let bib = author_bibliography(&idx, "Пелевин Виктор", false).unwrap();
+        assert!(bib.author.is_none());
+        assert!(bib.series.is_empty() && bib.standalone.is_empty());
+    }
+
+    #[test]
+    fn deleted_books_are_hidden_and_not_served() {
+        let lines = [
+            inp_line(1, "Гоголь,Николай,:", "Нос", "", 0),
+            testing::with_column(&inp_line(2, "Гоголь,Николай,:", "Вий", "", 0), "DEL", "1"),
+        ];
+        let (dir, idx) = testing::index_with("deleted", &lines);
+        testing::write_archive(
+            &dir.archives().join(testing::ARCHIVE),
+            &[("1.fb2", b"fb2"), ("2.fb2", b"fb2")],
+        );
+
+        let ids = |include_deleted| {
+            let options = SearchOptions {
+                include_deleted,
+                sort: SortOrder::Title,
+                ..SearchOptions::default()
+            };
+            let page = search_tantivy(&idx, "гоголь", &options).unwrap();
+            let ids: Vec<u64> = page.hits.iter().map(|hit| hit.book.id).collect();
+            (page.total, ids)
+        };
+        assert_eq!(ids(false), (1, vec![1]));
+        assert_eq!(ids(true), (2, vec![2, 1]));
+        assert!(get_info(&idx, 2).unwrap().deleted);
+
+        assert!(open_book_archive(&idx, &dir.archives(), 1).is_ok());
+        let err = open_book_archive(&idx, &dir.archives(), 2).err().unwrap();
+        assert!(err.is::<BookDeleted>(), "{}", err);
+    }
+
+    #[test]
+    fn find_in_archive_prefers_exact_name() {
+        let names = |list: &[&str]| list.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let files = names(&["10.fb2", "1.epub", "1.fb2"]);
+        assert_eq!(
+            find_in_archive(&files, 1, "1.fb2").as_deref(),
+            Some("1.fb2")
+        );
+        // Расширение в INP не совпало с архивом: берём любой `{id}.*`, но не `10.*`
+        let files = names(&["10.fb2", "1.FB2"]);
+        assert_eq!(
+            find_in_archive(&files, 1, "1.fb2").as_deref(),
+            Some("1.FB2")
+        );
+        assert_eq!(find_in_archive(&files, 2, "2.fb2"), None);
+        // Файл во вложенном каталоге - не книга `{id}`
+        let files = names(&["1.images/cover.jpg", "1.fb2.d/1.fb2"]);
+        assert_eq!(find_in_archive(&files, 1, "1.fb2"), None);
+    }
+
+    #[test]
+    fn file_name_and_format_follow_ext_column() {
+        let lines = [
+            testing::with_column(
+                &inp_line(1, "Гоголь,Николай,:", "Нос", "", 0),
+                "EXT",
+                "epub",
+            ),
+            testing::with_column(
+                &inp_line(2, "Гоголь,Николай,:", "Вий", "", 0),
+                "EXT",
+                "djvu",
+            ),
+            testing::with_column(&inp_line(3, "Гоголь,Николай,:", "Шинель", "", 0), "EXT", ""),
+        ];
+        let (dir, idx) = testing::index_with("ext_names", &lines);
+        testing::write_archive(
+            &dir.archives().join(testing::ARCHIVE),
+            &[("1.epub", b"epub"), ("2.DJVU", b"djvu"), ("3.fb2", b"fb2")],
+        );
+
+        let (_, name, ext) = open_book_archive(&idx, &dir.archives(), 1).unwrap();
+        assert_eq!((name.as_str(), ext.as_str()), ("1.epub", "epub"));
+        assert_eq!(
+            get_file_bytes(&idx, &dir.archives(), 1).unwrap(),
+            (b"epub".to_vec(), "epub".to_string())
+        );
+        // Имя в архиве отличается регистром: формат - по фактическому имени файла
+        let (_, name, _) = open_book_archive(&idx, &dir.archives(), 2).unwrap();
+        assert_eq!(name, "2.DJVU");
+        assert_eq!(get_file_bytes(&idx, &dir.archives(), 2).unwrap().1, "djvu");
+        // Без колонки EXT книга считается FB2
+        let (_, name, ext) = open_book_archive(&idx, &dir.archives(), 3).unwrap();
+        assert_eq!((name.as_str(), ext.as_str()), ("3.fb2", "fb2"));
+    }
+
+    #[test]
+    fn fuzzy_search_tolerates_typos_and_keeps_query_structure() {
+        let lines = [
+            inp_line(1, "Булгаков,Михаил,:", "Мастер и Маргарита", "", 0),
+            inp_line(2, "Иванов,Иван,:", "Мастер на все руки", "", 0),
+            inp_line(3, "Лукьяненко,Сергей,:", "Дозор", "", 0),
+            inp_line(4, "Петров,Пётр,:", "Позор", "", 0),
+        ];
+        let (_dir, idx) = testing::index_with("fuzzy", &lines);
+        let ids = |query: &str, fuzzy: bool| {
+            let options = SearchOptions {
+                fuzzy,
+                ..SearchOptions::default()
+            };
+            let page = search_tantivy(&idx, query, &options).unwrap();
+            page.hits.iter().map(|h| h.book.id).collect::<Vec<_>>()
+        };
+        let sorted = |mut ids: Vec<u64>| {
+            ids.sort_unstable();
+            ids
+        };
+
+        // Слово с опечаткой находится только в нечётком режиме
+        assert!(ids("маргарта", false).is_empty());
+        assert_eq!(ids("маргарта", true), [1]);
+
+        // Точное совпадение выше нечёткого
+        assert_eq!(ids("дозор", false), [3]);
+        assert_eq!(ids("дозор", true), [3, 4]);
+        assert_eq!(ids("позор", true), [4, 3]);
+
+        // `+` и `-` сохраняются: каждое слово запроса тоже ищется с опечатками
+        assert_eq!(sorted(ids("мастр", true)), [1, 2]);
+        assert_eq!(ids("+мастр -маргарта", true), [2]);
+        assert_eq!(ids("+мастр +руке", true), [2]);
+    }
+
+    /// Подсвеченные автор и название первой найденной книги
+    fn highlighted(idx: &OpenIndex, query: &str, fuzzy: bool) -> (String, String) {
+        let options = SearchOptions {
+            fuzzy,
+            highlight: Some(("[".to_string(), "]".to_string())),
+            ..SearchOptions::default()
+        };
+        let page = search_tantivy(idx, query, &options).unwrap();
+        let snippets = &page.hits[0].snippets;
+        (
+            snippets["author"].marked.clone(),
+            snippets["title"].marked.clone(),
+        )
+    }
+
+    #[test]
+    fn highlight_covers_fuzzy_and_translit_matches() {
+        let lines = [
+            inp_line(
+                1,
+                "Булгаков,Михаил,Афанасьевич:",
+                "Мастер и Маргарита",
+                "",
+                0,
+            ),
+            inp_line(2, "Толстой,Лев,:", "Война и мир", "", 0),
+        ];
+        let (_dir, idx) = testing::index_with("highlight", &lines);
+
+        let (author, _) = highlighted(&idx, "Булгков", true);
+        assert_eq!(author, "Михаил Афанасьевич [Булгаков]");
+        let (author, _) = highlighted(&idx, "bulgakov", false);
+        assert_eq!(author, "Михаил Афанасьевич [Булгаков]");
+        let (_, title) = highlighted(&idx, "master margarita", false);
+        assert_eq!(title, "[Мастер] и [Маргарита]");
+        let (_, title) = highlighted(&idx, "vojnaa", true);
+        assert_eq!(title, "[Война] и мир");
+    }
+
+    #[test]
+    fn html_markers_escape_text() {
+        let lines = [inp_line(1, "Гоголь,Николай,:", "Нос <script>&", "", 0)];
+        let (_dir, idx) = testing::index_with("highlight_html", &lines);
+        let marked = |markers: (&str, &str)| {
+            let options = SearchOptions {
+                highlight: Some((markers.0.to_string(), markers.1.to_string())),
+                ..SearchOptions::default()
+            };
+            let page = search_tantivy(&idx, "нос", &options).unwrap();
+            page.hits[0].snippets["title"].marked.clone()
+        };
+        assert_eq!(marked(("<b>", "</b>")), "<b>Нос</b> &lt;script&gt;&amp;");
+        assert_eq!(marked(("[", "]")), "[Нос] <script>&");
+    }
+}
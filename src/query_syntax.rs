@@ -0,0 +1,206 @@
+//! Поиск места ошибки в пользовательском запросе. Парсер Tantivy сообщает только
+//! о самом факте синтаксической ошибки, поэтому запрос повторно просматривается
+//! упрощённым сканером, который находит первую незакрытую или лишнюю конструкцию
+
+use tantivy::query::QueryParserError;
+
+/// Место ошибки: позиция символа (не байта) в запросе и её описание
+pub(crate) struct SyntaxIssue {
+    pub position: Option<usize>,
+    pub reason: String,
+}
+
+/// Символы, после которых может начинаться имя поля
+fn is_boundary(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '+' | '-')
+}
+
+/// Первая синтаксическая ошибка, которую удалось найти сканером
+fn scan(query: &str) -> Option<(usize, &'static str)> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut phrase_start: Option<usize> = None;
+    let mut range_start: Option<usize> = None;
+    let mut parens: Vec<usize> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if let Some(start) = phrase_start {
+            if c == '"' {
+                if i == start + 1 {
+                    return Some((start, "пустая фраза в кавычках"));
+                }
+                phrase_start = None;
+            }
+            i += 1;
+            continue;
+        }
+        match c {
+            // Экранированный символ не участвует в синтаксисе
+            '\\' => i += 1,
+            '"' => phrase_start = Some(i),
+            '(' => parens.push(i),
+            ')' if parens.pop().is_none() => {
+                return Some((i, "закрывающая скобка без открывающей"));
+            }
+            '[' | '{' if range_start.is_none() => range_start = Some(i),
+            ']' | '}' if range_start.take().is_none() => {
+                return Some((i, "конец диапазона без начала"));
+            }
+            ':' => {
+                let has_field = i > 0 && !is_boundary(chars[i - 1]) && chars[i - 1] != ':';
+                if !has_field {
+                    return Some((i, "двоеточие без имени поля"));
+                }
+                if chars.get(i + 1).is_none_or(|c| c.is_whitespace()) {
+                    return Some((i, "нет значения после имени поля"));
+                }
+            }
+            '^' if !chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) => {
+                return Some((i, "после '^' ожидается число"));
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if let Some(start) = phrase_start {
+        return Some((start, "незакрытая кавычка"));
+    }
+    if let Some(start) = range_start {
+        return Some((start, "незакрытый диапазон"));
+    }
+    parens.pop().map(|start| (start, "незакрытая скобка"))
+}
+
+/// Позиция упоминания поля `name:` в запросе
+fn field_position(query: &str, name: &str) -> Option<usize> {
+    let needle = format!("{}:", name);
+    query.match_indices(&needle).find_map(|(byte, _)| {
+        let prev = query[..byte].chars().next_back();
+        prev.is_none_or(is_boundary)
+            .then(|| query[..byte].chars().count())
+    })
+}
+
+/// Описание ошибки разбора запроса с позицией, если её удаётся определить
+pub(crate) fn describe(query: &str, error: &QueryParserError) -> SyntaxIssue {
+    let located = match error {
+        QueryParserError::FieldDoesNotExist(name) => field_position(query, name)
+            .map(|position| (position, format!("неизвестное поле '{}'", name))),
+        QueryParserError::FieldNotIndexed(name)
+        | QueryParserError::FieldDoesNotHavePositionsIndexed(name) => field_position(query, name)
+            .map(|position| (position, format!("по полю '{}' нельзя искать", name))),
+        _ => scan(query).map(|(position, reason)| (position, reason.to_string())),
+    };
+    match located {
+        Some((position, reason)) => SyntaxIssue {
+            position: Some(position),
+            reason,
+        },
+        None => SyntaxIssue {
+            position: None,
+            reason: error.to_string(),
+        },
+    }
+}
+
+/// Есть ли в запросе операторы: `+`/`-` перед словом, `AND`/`OR`/`NOT`, поля, фразы,
+/// диапазоны, группы, усиления. Запрос без них - просто набор слов
+pub(crate) fn has_operators(query: &str) -> bool {
+    query.split_whitespace().any(|word| {
+        word.starts_with(['+', '-'])
+            || matches!(word, "AND" | "OR" | "NOT")
+            || word.contains([':', '^', '"', '[', ']', '{', '}', '(', ')', '*', '\\'])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unclosed_constructs_point_at_their_start() {
+        assert_eq!(scan("\"война и мир"), Some((0, "незакрытая кавычка")));
+        assert_eq!(scan("автор \""), Some((6, "незакрытая кавычка")));
+        assert_eq!(scan("(толстой OR булгаков"), Some((0, "незакрытая скобка")));
+        assert_eq!(scan("((а) б"), Some((0, "незакрытая скобка")));
+        assert_eq!(scan("date:[2000 TO 2010"), Some((5, "незакрытый диапазон")));
+        assert_eq!(scan("size:{1 TO 5"), Some((5, "незакрытый диапазон")));
+    }
+
+    #[test]
+    fn stray_closers_point_at_themselves() {
+        assert_eq!(
+            scan("толстой)"),
+            Some((7, "закрывающая скобка без открывающей"))
+        );
+        assert_eq!(scan("война]"), Some((5, "конец диапазона без начала")));
+        assert_eq!(scan("\"\""), Some((0, "пустая фраза в кавычках")));
+    }
+
+    #[test]
+    fn field_syntax_errors() {
+        assert_eq!(scan(":война"), Some((0, "двоеточие без имени поля")));
+        assert_eq!(scan("война +:мир"), Some((7, "двоеточие без имени поля")));
+        assert_eq!(
+            scan("title: война"),
+            Some((5, "нет значения после имени поля"))
+        );
+        assert_eq!(scan("title:"), Some((5, "нет значения после имени поля")));
+        assert_eq!(scan("война^"), Some((5, "после '^' ожидается число")));
+        assert_eq!(scan("война^x"), Some((5, "после '^' ожидается число")));
+    }
+
+    #[test]
+    fn valid_queries_have_no_issue() {
+        assert_eq!(scan("+title:война -author:толстой"), None);
+        assert_eq!(scan("\"война и мир\"^2 (а OR б)"), None);
+        assert_eq!(scan("date:[2000 TO 2010]"), None);
+        // Экранированные символы не считаются синтаксисом
+        assert_eq!(scan("\\\"война \\(мир"), None);
+        // Двоеточие внутри фразы - часть текста
+        assert_eq!(scan("\"война: мир\""), None);
+    }
+
+    #[test]
+    fn positions_are_in_characters() {
+        assert_eq!(scan("Толстой ("), Some((8, "незакрытая скобка")));
+        assert_eq!(field_position("война genre:sf", "genre"), Some(6));
+        // Имя поля внутри слова - не упоминание поля
+        assert_eq!(field_position("subgenre:sf", "genre"), None);
+        assert_eq!(field_position("мир -genre:sf", "genre"), Some(5));
+    }
+
+    #[test]
+    fn describe_locates_unknown_fields_and_syntax_errors() {
+        let issue = describe(
+            "война genre:sf",
+            &QueryParserError::FieldDoesNotExist("genre".to_string()),
+        );
+        assert_eq!(issue.position, Some(6));
+        assert_eq!(issue.reason, "неизвестное поле 'genre'");
+
+        let issue = describe("(война", &QueryParserError::SyntaxError(String::new()));
+        assert_eq!(issue.position, Some(0));
+        assert_eq!(issue.reason, "незакрытая скобка");
+    }
+
+    #[test]
+    fn describe_falls_back_to_parser_message() {
+        let error = QueryParserError::SyntaxError("boom".to_string());
+        let issue = describe("война", &error);
+        assert_eq!(issue.position, None);
+        assert_eq!(issue.reason, error.to_string());
+    }
+
+    #[test]
+    fn operators_are_detected() {
+        assert!(!has_operators("Война и мир"));
+        assert!(!has_operators("Салтыков-Щедрин"));
+        assert!(has_operators("-толстой война"));
+        assert!(has_operators("+толстой +война"));
+        assert!(has_operators("толстой AND война"));
+        assert!(has_operators("title:война"));
+        assert!(has_operators("\"война и мир\""));
+        assert!(!has_operators("толстой and война"));
+    }
+}
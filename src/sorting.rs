@@ -0,0 +1,228 @@
+//! Сортировка результатов поиска по fast-полям вместо BM25.
+//! Книги с одинаковым ключом упорядочиваются по релевантности
+
+use std::cmp::Ordering;
+use std::sync::Arc;
+use tantivy::collector::{Count, FilterCollector, ScoreSegmentTweaker, ScoreTweaker, TopDocs};
+use tantivy::fastfield::Column;
+use tantivy::query::Query;
+use tantivy::schema::Field;
+use tantivy::{DateTime, DocAddress, DocId, Score, Searcher, SegmentReader, TantivyError};
+
+use crate::{collation, Author, BookFields};
+
+/// Найденная книга с ключом сортировки и BM25
+type Sorted = ((u64, Score), DocAddress);
+
+/// Порядок сортировки результатов
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum SortOrder {
+    #[default]
+    Relevance,
+    Date,   // Сначала новые
+    Title,  // По алфавиту
+    Author, // По фамилии первого автора
+    Serno,  // По номеру в серии
+    Size,   // Сначала большие
+    Rating, // Сначала с высоким LIBRATE
+}
+
+impl SortOrder {
+    pub(crate) fn parse(order: &str) -> Result<Self, String> {
+        match order {
+            "relevance" => Ok(SortOrder::Relevance),
+            "date" => Ok(SortOrder::Date),
+            "title" => Ok(SortOrder::Title),
+            "author" => Ok(SortOrder::Author),
+            "serno" => Ok(SortOrder::Serno),
+            "size" => Ok(SortOrder::Size),
+            "rating" => Ok(SortOrder::Rating),
+            _ => Err(format!(
+                "Неизвестная сортировка '{}', ожидается 'relevance', 'date', 'title', \
+                 'author', 'serno', 'size' или 'rating'",
+                order
+            )),
+        }
+    }
+
+    /// Естественное направление: даты, размеры и рейтинги - по убыванию, остальное - по возрастанию
+    fn descending(self) -> bool {
+        matches!(self, SortOrder::Date | SortOrder::Size | SortOrder::Rating)
+    }
+}
+
+enum SortColumn {
+    U64(Arc<dyn Column<u64>>),
+    Date(Arc<dyn Column<DateTime>>),
+}
+
+/// Подмена score ключом сортировки: `(ключ, BM25)`, больший ключ идёт первым
+#[derive(Clone)]
+pub(crate) struct SortTweaker {
+    fields: BookFields,
+    order: SortOrder,
+    descending: bool,
+}
+
+impl SortTweaker {
+    /// `reverse` меняет естественное направление сортировки на обратное
+    pub(crate) fn new(fields: BookFields, order: SortOrder, reverse: bool) -> Self {
+        SortTweaker {
+            fields,
+            order,
+            descending: order.descending() != reverse,
+        }
+    }
+
+    /// Страница `sorted[offset..offset + limit]`, где `sorted` - первые `offset + limit`
+    /// книг по ключу. Ключ названия и автора различает только первые 9 символов,
+    /// поэтому такие книги досортировываются полным сравнением строк, а к ним
+    /// добавляются не вошедшие в `sorted` книги с тем же последним ключом
+    pub(crate) fn page(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        mut sorted: Vec<Sorted>,
+        offset: usize,
+        limit: usize,
+    ) -> tantivy::Result<Vec<(Score, DocAddress)>> {
+        let field = match self.order {
+            SortOrder::Title => Some(self.fields.title_sort),
+            SortOrder::Author => Some(self.fields.author_sort),
+            _ => None,
+        };
+        if let Some(field) = field {
+            if sorted.len() == offset + limit {
+                self.add_ties(searcher, query, field, &mut sorted)?;
+            }
+            self.collate(searcher, &mut sorted)?;
+        }
+        // Ключ сортировки не возвращаем: в выдаче остаётся BM25 score
+        Ok(sorted
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|((_, score), doc_address)| (score, doc_address))
+            .collect())
+    }
+
+    /// Все найденные книги с последним ключом `sorted`, включая не попавшие в лимит
+    fn add_ties(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        field: Field,
+        sorted: &mut Vec<Sorted>,
+    ) -> tantivy::Result<()> {
+        let Some(&((last, _), _)) = sorted.last() else {
+            return Ok(());
+        };
+        // Книги без названия или автора и так в конце, между собой - по релевантности
+        if last == 0 {
+            return Ok(());
+        }
+        let value = if self.descending {
+            last
+        } else {
+            u64::MAX - last
+        };
+        let same_key = move |v: u64| v == value;
+        let tied = searcher.search(query, &FilterCollector::new(field, same_key, Count))?;
+        let collected = sorted.iter().filter(|((key, _), _)| *key == last).count();
+        if tied <= collected {
+            return Ok(());
+        }
+        let all = searcher.search(
+            query,
+            &FilterCollector::new(field, same_key, TopDocs::with_limit(tied)),
+        )?;
+        sorted.retain(|((key, _), _)| *key != last);
+        sorted.extend(
+            all.into_iter()
+                .map(|(score, doc_address)| ((last, score), doc_address)),
+        );
+        Ok(())
+    }
+
+    /// Порядок внутри одинаковых ключей: полное название или имя первого автора,
+    /// затем релевантность
+    fn collate(&self, searcher: &Searcher, sorted: &mut Vec<Sorted>) -> tantivy::Result<()> {
+        let mut texts = Vec::with_capacity(sorted.len());
+        for (_, doc_address) in sorted.iter() {
+            let book = self.fields.read_book(&searcher.doc(*doc_address)?);
+            texts.push(match self.order {
+                SortOrder::Author => book
+                    .authors
+                    .first()
+                    .map(Author::sort_name)
+                    .unwrap_or_default(),
+                _ => book.book_title,
+            });
+        }
+        let mut entries: Vec<(String, Sorted)> = texts.into_iter().zip(sorted.drain(..)).collect();
+        entries.sort_by(
+            |(a_text, ((a_key, a_score), _)), (b_text, ((b_key, b_score), _))| {
+                let text = match collation::compare(a_text, b_text) {
+                    order if self.descending => order.reverse(),
+                    order => order,
+                };
+                b_key
+                    .cmp(a_key)
+                    .then(text)
+                    .then_with(|| b_score.partial_cmp(a_score).unwrap_or(Ordering::Equal))
+            },
+        );
+        sorted.extend(entries.into_iter().map(|(_, entry)| entry));
+        Ok(())
+    }
+}
+
+pub(crate) struct SortSegmentTweaker {
+    column: SortColumn,
+    descending: bool,
+}
+
+impl ScoreTweaker<(u64, Score)> for SortTweaker {
+    type Child = SortSegmentTweaker;
+
+    fn segment_tweaker(&self, segment_reader: &SegmentReader) -> tantivy::Result<Self::Child> {
+        let fast_fields = segment_reader.fast_fields();
+        let column = match self.order {
+            SortOrder::Date => SortColumn::Date(fast_fields.date(self.fields.date)?),
+            SortOrder::Title => SortColumn::U64(fast_fields.u64(self.fields.title_sort)?),
+            SortOrder::Author => SortColumn::U64(fast_fields.u64(self.fields.author_sort)?),
+            SortOrder::Serno => SortColumn::U64(fast_fields.u64(self.fields.serno)?),
+            SortOrder::Size => SortColumn::U64(fast_fields.u64(self.fields.size)?),
+            SortOrder::Rating => SortColumn::U64(fast_fields.u64(self.fields.rating)?),
+            SortOrder::Relevance => {
+                return Err(TantivyError::InvalidArgument(
+                    "Сортировка по релевантности не использует ключ".to_string(),
+                ))
+            }
+        };
+        Ok(SortSegmentTweaker {
+            column,
+            descending: self.descending,
+        })
+    }
+}
+
+impl ScoreSegmentTweaker<(u64, Score)> for SortSegmentTweaker {
+    fn score(&mut self, doc: DocId, score: Score) -> (u64, Score) {
+        let value = match &self.column {
+            SortColumn::U64(column) => column.get_val(doc),
+            SortColumn::Date(column) => match column.get_val(doc).into_timestamp_secs() {
+                0 => 0,
+                // Сдвиг знакового времени в беззнаковый порядок
+                secs => (secs as u64) ^ (1 << 63),
+            },
+        };
+        // Нулевое значение - нет даты, номера, названия: такие книги всегда в конце
+        let key = match value {
+            0 => 0,
+            value if self.descending => value,
+            value => u64::MAX - value,
+        };
+        (key, score)
+    }
+}
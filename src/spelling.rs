@@ -0,0 +1,226 @@
+//! «Возможно, вы имели в виду»: исправление опечаток в запросе по словарю термов
+//! поля `spell`, где слова авторов и названий лежат без стемминга. Слово, которого
+//! нет в словаре, заменяется ближайшим по Левенштейну термом, а среди равноудалённых -
+//! самым частым
+
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tantivy::schema::Field;
+use tantivy::{Searcher, TantivyError, Term};
+use tantivy_fst::Automaton;
+
+use crate::{analyzer, fuzzy_distance, BookFields};
+
+/// Операторы синтаксиса запроса, их не исправляем
+const OPERATORS: [&str; 3] = ["AND", "OR", "NOT"];
+
+/// Автомат Левенштейна для обхода словаря термов
+struct Levenshtein(DFA);
+
+impl Automaton for Levenshtein {
+    type State = u32;
+
+    fn start(&self) -> u32 {
+        self.0.initial_state()
+    }
+
+    fn is_match(&self, state: &u32) -> bool {
+        matches!(self.0.distance(*state), Distance::Exact(_))
+    }
+
+    fn can_match(&self, state: &u32) -> bool {
+        *state != levenshtein_automata::SINK_STATE
+    }
+
+    fn accept(&self, state: &u32, byte: u8) -> u32 {
+        self.0.transition(*state, byte)
+    }
+}
+
+/// Построители автоматов на одну и две правки; перестановка букв - одна правка.
+/// Построение дорогое, поэтому делается один раз
+fn automaton(word: &str, distance: u8) -> Levenshtein {
+    static BUILDERS: OnceLock<[LevenshteinAutomatonBuilder; 2]> = OnceLock::new();
+    let builders = BUILDERS.get_or_init(|| {
+        [
+            LevenshteinAutomatonBuilder::new(1, true),
+            LevenshteinAutomatonBuilder::new(2, true),
+        ]
+    });
+    Levenshtein(builders[usize::from(distance) - 1].build_dfa(word))
+}
+
+/// Запрос, в котором слова с опечатками заменены словами из индекса.
+/// `None`, если исправлять нечего или замен не нашлось
+pub(crate) fn correct(
+    searcher: &Searcher,
+    fields: &BookFields,
+    query: &str,
+) -> Result<Option<String>, TantivyError> {
+    let mut corrected = String::with_capacity(query.len());
+    let mut last = 0;
+    let mut changed = false;
+    for token in analyzer::spell_tokens(query) {
+        let original = &query[token.offset_from..token.offset_to];
+        // Имена полей (`author:`) и операторы - часть синтаксиса, а не слова
+        if query[token.offset_to..].starts_with(':') || OPERATORS.contains(&original) {
+            continue;
+        }
+        let Some(replacement) = closest_term(searcher, fields.spell, &token.text)? else {
+            continue;
+        };
+        corrected.push_str(&query[last..token.offset_from]);
+        corrected.push_str(&match_case(original, &replacement));
+        last = token.offset_to;
+        changed = true;
+    }
+    if !changed {
+        return Ok(None);
+    }
+    corrected.push_str(&query[last..]);
+    Ok(Some(corrected))
+}
+
+/// Ближайший терм словаря для слова, которого в словаре нет
+fn closest_term(
+    searcher: &Searcher,
+    field: Field,
+    word: &str,
+) -> Result<Option<String>, TantivyError> {
+    let distance = fuzzy_distance(word);
+    if distance == 0 || word.chars().any(|c| c.is_numeric()) {
+        return Ok(None);
+    }
+    if searcher.doc_freq(&Term::from_field_text(field, word))? > 0 {
+        return Ok(None);
+    }
+
+    let candidates = similar_terms(searcher, field, word, distance)?;
+    let automaton = automaton(word, distance);
+    let edits = |term: &str| match automaton.0.eval(term) {
+        Distance::Exact(d) => d,
+        Distance::AtLeast(d) => d,
+    };
+    Ok(candidates
+        .into_iter()
+        .min_by(|a, b| {
+            edits(&a.0)
+                .cmp(&edits(&b.0))
+                .then_with(|| b.1.cmp(&a.1))
+                .then_with(|| a.0.cmp(&b.0))
+        })
+        .map(|(term, _)| term))
+}
+
+/// Термы словаря поля не дальше `distance` правок от `word` с числом документов, -
+/// те же, что находит `FuzzyTermQuery` с перестановкой букв за одну правку
+pub(crate) fn similar_terms(
+    searcher: &Searcher,
+    field: Field,
+    word: &str,
+    distance: u8,
+) -> Result<HashMap<String, u64>, TantivyError> {
+    let automaton = automaton(word, distance);
+    let mut terms_freq: HashMap<String, u64> = HashMap::new();
+    for segment_reader in searcher.segment_readers() {
+        let inverted_index = segment_reader.inverted_index(field)?;
+        let mut terms = inverted_index.terms().search(&automaton).into_stream()?;
+        while terms.advance() {
+            if let Ok(term) = std::str::from_utf8(terms.key()) {
+                *terms_freq.entry(term.to_string()).or_insert(0) +=
+                    u64::from(terms.value().doc_freq);
+            }
+        }
+    }
+    Ok(terms_freq)
+}
+
+/// Регистр исправленного слова по исходному: «Булгков» -> «Булгаков», «БУЛГКОВ» -> «БУЛГАКОВ»
+fn match_case(original: &str, replacement: &str) -> String {
+    let letters = || original.chars().filter(|c| c.is_alphabetic());
+    if letters().count() > 1 && letters().all(char::is_uppercase) {
+        return replacement.to_uppercase();
+    }
+    let mut chars = replacement.chars();
+    match (original.chars().next(), chars.next()) {
+        (Some(first), Some(c)) if first.is_uppercase() => c.to_uppercase().chain(chars).collect(),
+        _ => replacement.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, inp_line};
+    use crate::{search_tantivy, SearchOptions};
+
+    /// Индекс с книгами Булгакова и Толстого; книга Гоголя удалена
+    fn library(name: &str) -> (testing::TempDir, crate::OpenIndex) {
+        let lines = [
+            inp_line(1, "Булгаков,Михаил,:", "Мастер и Маргарита", "", 0),
+            inp_line(2, "Толстой,Лев,:", "Война и мир", "", 0),
+            inp_line(3, "Уэллс,Герберт,:", "Tittle", "", 0),
+            testing::with_column(
+                &inp_line(4, "Гоголь,Николай,:", "Шинель", "", 0),
+                "DEL",
+                "1",
+            ),
+        ];
+        testing::index_with(name, &lines)
+    }
+
+    fn corrected(idx: &crate::OpenIndex, query: &str) -> Option<String> {
+        correct(&idx.reader.searcher(), &idx.fields, query).unwrap()
+    }
+
+    #[test]
+    fn corrected_word_keeps_case() {
+        let (_dir, idx) = library("spelling_case");
+        assert_eq!(corrected(&idx, "Булгков").as_deref(), Some("Булгаков"));
+        assert_eq!(corrected(&idx, "БУЛГКОВ").as_deref(), Some("БУЛГАКОВ"));
+        assert_eq!(
+            corrected(&idx, "мастр и маргрита").as_deref(),
+            Some("мастер и маргарита")
+        );
+        // Слова из словаря не исправляются
+        assert_eq!(corrected(&idx, "Булгаков"), None);
+    }
+
+    #[test]
+    fn field_names_and_operators_are_kept() {
+        let (_dir, idx) = library("spelling_syntax");
+        // Без проверки двоеточия `title` исправился бы на `tittle`
+        assert_eq!(
+            corrected(&idx, "title:Мастр AND Булгков OR Толстй").as_deref(),
+            Some("title:Мастер AND Булгаков OR Толстой")
+        );
+        assert_eq!(corrected(&idx, "title:Мастер"), None);
+    }
+
+    #[test]
+    fn short_words_are_not_corrected() {
+        let (_dir, idx) = library("spelling_short");
+        // «мор» в одной правке от «мир», но слова до трёх букв ищутся точно
+        assert_eq!(corrected(&idx, "мор"), None);
+        assert_eq!(corrected(&idx, "война мор"), None);
+        assert_eq!(corrected(&idx, "вйна мор").as_deref(), Some("война мор"));
+    }
+
+    #[test]
+    fn suggestion_requires_more_books() {
+        let (_dir, idx) = library("spelling_suggestion");
+        let suggestion = |query: &str, include_deleted| {
+            let options = SearchOptions {
+                include_deleted,
+                ..SearchOptions::default()
+            };
+            search_tantivy(&idx, query, &options).unwrap().suggestion
+        };
+        assert_eq!(suggestion("Булгков", false).as_deref(), Some("Булгаков"));
+        // Исправленный запрос находит только удалённую книгу - не лучше исходного
+        assert_eq!(corrected(&idx, "Гголь").as_deref(), Some("Гоголь"));
+        assert_eq!(suggestion("Гголь", false), None);
+        assert_eq!(suggestion("Гголь", true).as_deref(), Some("Гоголь"));
+    }
+}
@@ -0,0 +1,335 @@
+//! Подсказки при вводе запроса: авторы, названия и серии, чей нормализованный
+//! ключ начинается с набранного текста. Ключи лежат в словаре термов фасетных
+//! полей `/ключ/написание`, поэтому подсказка - это сканирование диапазона FST
+//! без чтения документов. Книги без DEL=1 продублированы в полях `*_key_live`,
+//! так что и без `include_deleted` число книг обычно берётся прямо из словаря
+
+use std::collections::HashMap;
+use tantivy::schema::{Field, IndexRecordOption};
+use tantivy::{DocSet, Searcher, TantivyError, TERMINATED};
+
+use crate::{collation, BookFields, Suggestion};
+
+/// Разделитель сегментов пути фасета в словаре термов (в Tantivy он не публичный)
+const FACET_SEP: char = '\u{0}';
+
+/// Что подсказывать
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SuggestKind {
+    Author,
+    Title,
+    Series,
+}
+
+impl SuggestKind {
+    pub(crate) fn parse(kind: &str) -> Result<Self, String> {
+        match kind {
+            "author" => Ok(SuggestKind::Author),
+            "title" => Ok(SuggestKind::Title),
+            "series" => Ok(SuggestKind::Series),
+            _ => Err(format!(
+                "Неизвестный вид подсказок '{}', ожидается 'author', 'title' или 'series'",
+                kind
+            )),
+        }
+    }
+
+    /// Поле ключей; без `include_deleted` - поле только с книгами без DEL=1
+    fn field(self, fields: &BookFields, include_deleted: bool) -> Field {
+        match (self, include_deleted) {
+            (SuggestKind::Author, true) => fields.author_key,
+            (SuggestKind::Author, false) => fields.author_key_live,
+            (SuggestKind::Title, true) => fields.title_key,
+            (SuggestKind::Title, false) => fields.title_key_live,
+            (SuggestKind::Series, true) => fields.series_key,
+            (SuggestKind::Series, false) => fields.series_key_live,
+        }
+    }
+}
+
+/// Число книг по каждому терму поля, начинающемуся с `prefix`, во всех сегментах.
+/// В сегменте без удалённых документов число берётся из словаря, иначе - по живым
+/// документам списка: словарь помнит и книги, удалённые при инкрементальном
+/// обновлении, пока сегменты не слиты
+fn prefix_terms(
+    searcher: &Searcher,
+    field: Field,
+    prefix: &str,
+    mut visit: impl FnMut(&str, u64),
+) -> Result<(), TantivyError> {
+    for segment_reader in searcher.segment_readers() {
+        let alive_bitset = segment_reader.alive_bitset();
+        let inverted_index = segment_reader.inverted_index(field)?;
+        let mut terms = inverted_index
+            .terms()
+            .range()
+            .ge(prefix.as_bytes())
+            .into_stream()?;
+        while terms.advance() {
+            if !terms.key().starts_with(prefix.as_bytes()) {
+                break;
+            }
+            let Ok(term) = std::str::from_utf8(terms.key()) else {
+                continue;
+            };
+            let doc_freq = match alive_bitset {
+                None => u64::from(terms.value().doc_freq),
+                Some(bitset) => {
+                    let mut postings = inverted_index
+                        .read_postings_from_terminfo(terms.value(), IndexRecordOption::Basic)?;
+                    let mut doc_freq = 0u64;
+                    let mut doc = postings.doc();
+                    while doc != TERMINATED {
+                        if bitset.is_alive(doc) {
+                            doc_freq += 1;
+                        }
+                        doc = postings.advance();
+                    }
+                    doc_freq
+                }
+            };
+            if doc_freq > 0 {
+                visit(term, doc_freq);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Подсказки по префиксу: сначала ключи с большим числом книг.
+/// Книги с DEL=1 учитываются только при `include_deleted`. Сначала считаются
+/// только ключи, написания читаются лишь для попавших в `limit`
+pub(crate) fn suggest(
+    searcher: &Searcher,
+    fields: &BookFields,
+    prefix: &str,
+    kind: SuggestKind,
+    limit: usize,
+    include_deleted: bool,
+) -> Result<Vec<Suggestion>, TantivyError> {
+    let prefix = collation::normalize(prefix);
+    if prefix.is_empty() || limit == 0 {
+        return Ok(Vec::new());
+    }
+    let field = kind.field(fields, include_deleted);
+
+    // Терм `ключ` - все книги ключа, `ключ\0написание` - книги с этим написанием
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    prefix_terms(searcher, field, &prefix, |term, doc_freq| {
+        if !term.contains(FACET_SEP) {
+            *counts.entry(term.to_string()).or_insert(0) += doc_freq;
+        }
+    })?;
+    let mut keys: Vec<(String, u64)> = counts.into_iter().collect();
+    let order = |a: &(String, u64), b: &(String, u64)| {
+        b.1.cmp(&a.1)
+            .then_with(|| collation::compare(&a.0, &b.0))
+            .then_with(|| a.0.cmp(&b.0))
+    };
+    if keys.len() > limit {
+        keys.select_nth_unstable_by(limit - 1, order);
+        keys.truncate(limit);
+    }
+    keys.sort_by(order);
+
+    let mut suggestions = Vec::with_capacity(keys.len());
+    for (key, count) in keys {
+        let mut spellings: HashMap<String, u64> = HashMap::new();
+        let key_prefix = format!("{}{}", key, FACET_SEP);
+        prefix_terms(searcher, field, &key_prefix, |term, doc_freq| {
+            *spellings
+                .entry(term[key_prefix.len()..].to_string())
+                .or_insert(0) += doc_freq;
+        })?;
+        // Показываем самое частое написание
+        let Some((text, _)) = spellings
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        else {
+            continue;
+        };
+        suggestions.push(Suggestion { text, key, count });
+    }
+    Ok(suggestions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, inp_line};
+
+    fn titles(
+        searcher: &Searcher,
+        fields: &BookFields,
+        prefix: &str,
+    ) -> Vec<(String, String, u64)> {
+        suggest(searcher, fields, prefix, SuggestKind::Title, 10, true)
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.key, s.text, s.count))
+            .collect()
+    }
+
+    #[test]
+    fn facet_terms_are_split_into_key_and_spelling() {
+        let lines = [
+            inp_line(1, "Толстой,Лев,:", "Война и мир", "", 0),
+            inp_line(2, "Толстой,Лев,:", "Война и мир", "", 0),
+            inp_line(3, "Толстой,Лев,:", "ВОЙНА И МИР", "", 0),
+            inp_line(4, "Уэллс,Герберт,:", "Война миров", "", 0),
+            inp_line(5, "Толстой,Лев,:", "Анна Каренина", "", 0),
+        ];
+        let (dir, idx) = testing::index_with("suggest", &lines);
+        let expected = vec![
+            ("война и мир".to_string(), "Война и мир".to_string(), 3),
+            ("война миров".to_string(), "Война миров".to_string(), 1),
+        ];
+        assert_eq!(
+            titles(&idx.reader.searcher(), &idx.fields, "ВОЙНА"),
+            expected
+        );
+
+        // Книги, удалённые инкрементальным обновлением, не считаются до слияния сегментов
+        testing::build(&dir, &idx, &lines[2..], true);
+        let expected = vec![
+            ("война и мир".to_string(), "ВОЙНА И МИР".to_string(), 1),
+            ("война миров".to_string(), "Война миров".to_string(), 1),
+        ];
+        assert_eq!(
+            titles(&idx.reader.searcher(), &idx.fields, "война"),
+            expected
+        );
+    }
+
+    #[test]
+    fn authors_are_counted_without_deleted_books() {
+        let lines = [
+            inp_line(
+                1,
+                "Стругацкий,Аркадий,Натанович:",
+                "Пикник на обочине",
+                "",
+                0,
+            ),
+            inp_line(
+                2,
+                "Стругацкий,Аркадий,Натанович:",
+                "Трудно быть богом",
+                "",
+                0,
+            ),
+            inp_line(
+                3,
+                "Стругацкий,Борис,Натанович:",
+                "Поиск предназначения",
+                "",
+                0,
+            ),
+            testing::with_column(
+                &inp_line(
+                    4,
+                    "Стругацкий,Борис,Натанович:",
+                    "Бессильные мира сего",
+                    "",
+                    0,
+                ),
+                "DEL",
+                "1",
+            ),
+            testing::with_column(
+                &inp_line(5, "Стругацкий,Борис,Натанович:", "Град обреченный", "", 0),
+                "DEL",
+                "1",
+            ),
+            inp_line(6, "Толстой,Лев,:", "Война и мир", "", 0),
+        ];
+        let (_dir, idx) = testing::index_with("suggest_authors", &lines);
+        let searcher = idx.reader.searcher();
+        let authors = |include_deleted| {
+            suggest(
+                &searcher,
+                &idx.fields,
+                "стругацк",
+                SuggestKind::Author,
+                10,
+                include_deleted,
+            )
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.key, s.count))
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            authors(false),
+            [
+                ("стругацкий аркадий натанович".to_string(), 2),
+                ("стругацкий борис натанович".to_string(), 1),
+            ]
+        );
+        assert_eq!(
+            authors(true),
+            [
+                ("стругацкий борис натанович".to_string(), 3),
+                ("стругацкий аркадий натанович".to_string(), 2),
+            ]
+        );
+
+        // Ключ подсказки - готовый аргумент для books_by_author
+        let top = suggest(
+            &searcher,
+            &idx.fields,
+            "Стругацк",
+            SuggestKind::Author,
+            1,
+            false,
+        )
+        .unwrap();
+        assert_eq!(top.len(), 1);
+        let bib = crate::author_bibliography(&idx, &top[0].key, false).unwrap();
+        let mut ids: Vec<u64> = bib.standalone.iter().map(|b| b.id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, [1, 2]);
+    }
+
+    #[test]
+    fn series_keep_most_common_spelling_and_limit() {
+        let lines = [
+            inp_line(1, "Лукьяненко,Сергей,:", "Ночной дозор", "Дозоры", 1),
+            inp_line(2, "Лукьяненко,Сергей,:", "Дневной дозор", "Дозоры", 2),
+            inp_line(3, "Лукьяненко,Сергей,:", "Новый дозор", "ДОЗОРЫ", 5),
+            inp_line(4, "Васильев,Владимир,:", "Лик чёрной Пальмиры", "Дозоры", 4),
+            inp_line(5, "Лукьяненко,Сергей,:", "Черновик", "Дорога", 1),
+            inp_line(6, "Лукьяненко,Сергей,:", "Чистовик", "Дорога", 2),
+            inp_line(7, "Иванов,Иван,:", "Лес", "Дом", 1),
+        ];
+        let (_dir, idx) = testing::index_with("suggest_series", &lines);
+        let searcher = idx.reader.searcher();
+        let series = |limit| {
+            suggest(
+                &searcher,
+                &idx.fields,
+                "до",
+                SuggestKind::Series,
+                limit,
+                false,
+            )
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.text, s.count))
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            series(10),
+            [
+                ("Дозоры".to_string(), 4),
+                ("Дорога".to_string(), 2),
+                ("Дом".to_string(), 1),
+            ]
+        );
+        assert_eq!(
+            series(2),
+            [("Дозоры".to_string(), 4), ("Дорога".to_string(), 2)]
+        );
+        assert!(series(0).is_empty());
+    }
+}
@@ -0,0 +1,131 @@
+//! Вспомогательное для тестов: временные каталоги и индексы из строк INP
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tantivy::ReloadPolicy;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::{build_tantivy_index, open_or_create_index, BuildReport, InpLayout, OpenIndex};
+
+/// Архив, в котором по умолчанию лежат книги из `write_inpx`
+pub(crate) const ARCHIVE: &str = "fb2-000001-000100.zip";
+
+/// Временный каталог, удаляется вместе со значением
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("flib_rs_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(path.join("archive")).unwrap();
+        fs::write(path.join("archive").join(ARCHIVE), b"").unwrap();
+        TempDir(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Каталог с zip-архивами книг
+    pub(crate) fn archives(&self) -> PathBuf {
+        self.0.join("archive")
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Строка INP в раскладке Flibusta по умолчанию
+pub(crate) fn inp_line(id: u64, author: &str, title: &str, series: &str, serno: u64) -> String {
+    let serno = if serno == 0 {
+        String::new()
+    } else {
+        serno.to_string()
+    };
+    let id = id.to_string();
+    [
+        author,
+        "sf_fantasy:",
+        title,
+        series,
+        &serno,
+        &id,
+        "1000",
+        &id,
+        "0",
+        "fb2",
+        "2020-01-01",
+        "ru",
+        "",
+        "",
+        "",
+    ]
+    .join("\x04")
+}
+
+/// Строка INP с заменённым значением колонки `name` (раскладка по умолчанию)
+pub(crate) fn with_column(line: &str, name: &str, value: &str) -> String {
+    let layout = InpLayout::default();
+    let mut fields: Vec<&str> = line.split('\x04').collect();
+    fields[layout.columns[name]] = value;
+    fields.join("\x04")
+}
+
+/// INPX с одним .inp файлом из строк `lines`
+pub(crate) fn write_inpx(path: &Path, lines: &[String]) {
+    let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+    let inp_name = ARCHIVE.replace(".zip", ".inp");
+    zip.start_file(
+        inp_name,
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )
+    .unwrap();
+    zip.write_all(lines.join("\r\n").as_bytes()).unwrap();
+    zip.finish().unwrap();
+}
+
+/// Zip-архив книг с файлами `files` (имя, содержимое)
+pub(crate) fn write_archive(path: &Path, files: &[(&str, &[u8])]) {
+    let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+    for (name, contents) in files {
+        zip.start_file(
+            *name,
+            SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+        )
+        .unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+/// Индекс в `dir/idx`
+pub(crate) fn open_index(dir: &TempDir) -> OpenIndex {
+    let path = dir.path().join("idx").to_string_lossy().to_string();
+    let index = open_or_create_index(&path).unwrap();
+    OpenIndex::new(&path, index, ReloadPolicy::Manual).unwrap()
+}
+
+/// Сборка индекса из строк INP, как это делает `build_index`
+pub(crate) fn build(
+    dir: &TempDir,
+    idx: &OpenIndex,
+    lines: &[String],
+    incremental: bool,
+) -> BuildReport {
+    let inpx = dir.path().join("lib.inpx");
+    write_inpx(&inpx, lines);
+    build_tantivy_index(idx, inpx, dir.archives(), 0, incremental).unwrap()
+}
+
+/// Новый индекс с книгами из `lines`
+pub(crate) fn index_with(name: &str, lines: &[String]) -> (TempDir, OpenIndex) {
+    let dir = TempDir::new(name);
+    let idx = open_index(&dir);
+    build(&dir, &idx, lines, false);
+    (dir, idx)
+}
@@ -0,0 +1,159 @@
+//! Транслитерация кириллицы в латиницу для поиска между алфавитами.
+//! Авторы и названия индексируются в нескольких распространённых латинских написаниях,
+//! запрос романизируется теми же схемами, поэтому латинский запрос находит кириллические
+//! книги, а кириллический - книги с латинскими названиями
+
+/// Схема романизации
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Scheme {
+    /// Бытовая: `й` -> `y`, окончания `-ий`/`-ый` -> `y` (Dostoevsky, Tolstoy)
+    Common,
+    /// BGN/PCGN без сокращений: `-ий` -> `iy` (Dostoevskiy)
+    Bgn,
+    /// ГОСТ 16876-71 / «немецкая»: `й` -> `j`, `х` -> `h`, `ц` -> `c` (Tolstoj, Mihail)
+    J,
+}
+
+const SCHEMES: [Scheme; 3] = [Scheme::Common, Scheme::Bgn, Scheme::J];
+
+/// Латинское написание одной строчной кириллической буквы, `None` для прочих символов
+fn letter(c: char, scheme: Scheme) -> Option<&'static str> {
+    let j = scheme == Scheme::J;
+    Some(match c {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' => "g",
+        'д' => "d",
+        'е' | 'э' => "e",
+        'ё' if j => "jo",
+        'ё' => "e",
+        'ж' => "zh",
+        'з' => "z",
+        'и' => "i",
+        'й' if j => "j",
+        'й' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' if j => "h",
+        'х' => "kh",
+        'ц' if j => "c",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' if j => "sch",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ы' => "y",
+        'ю' if j => "ju",
+        'ю' => "yu",
+        'я' if j => "ja",
+        'я' => "ya",
+        _ => return None,
+    })
+}
+
+fn is_cyrillic(c: char) -> bool {
+    matches!(c, 'а'..='я' | 'А'..='Я' | 'ё' | 'Ё')
+}
+
+/// Романизация текста по одной схеме; не-кириллические символы остаются как есть
+fn romanize(text: &str, scheme: Scheme) -> String {
+    let chars: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        // Бытовое написание окончаний: «Достоевский» -> «Dostoevsky»
+        if scheme == Scheme::Common
+            && matches!(c, 'и' | 'ы')
+            && chars.get(i + 1) == Some(&'й')
+            && !chars.get(i + 2).is_some_and(|c| c.is_alphanumeric())
+        {
+            out.push('y');
+            i += 2;
+            continue;
+        }
+        match letter(c, scheme) {
+            Some(latin) => out.push_str(latin),
+            None => out.push(c),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Все различающиеся латинские написания текста (для латинского текста - он сам)
+pub(crate) fn romanize_variants(text: &str) -> Vec<String> {
+    if !text.chars().any(is_cyrillic) {
+        return vec![text.to_lowercase()];
+    }
+    let mut variants: Vec<String> = Vec::with_capacity(SCHEMES.len());
+    for scheme in SCHEMES {
+        let variant = romanize(text, scheme);
+        if !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    }
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schemes_differ_in_y_kh_and_endings() {
+        assert_eq!(romanize("Достоевский", Scheme::Common), "dostoevsky");
+        assert_eq!(romanize("Достоевский", Scheme::Bgn), "dostoevskiy");
+        assert_eq!(romanize("Достоевский", Scheme::J), "dostoevskij");
+        assert_eq!(
+            romanize("Михаил Хрущёв", Scheme::Common),
+            "mikhail khrushchev"
+        );
+        assert_eq!(romanize("Михаил Хрущёв", Scheme::J), "mihail hruschjov");
+        assert_eq!(romanize("Цветаева", Scheme::Bgn), "tsvetaeva");
+        assert_eq!(romanize("Цветаева", Scheme::J), "cvetaeva");
+    }
+
+    #[test]
+    fn ending_rule_applies_only_at_word_end() {
+        assert_eq!(romanize("Красный кий", Scheme::Common), "krasny ky");
+        assert_eq!(romanize("Бийск", Scheme::Common), "biysk");
+        assert_eq!(romanize("Горький-2", Scheme::Common), "gorky-2");
+        assert_eq!(romanize("Бийск", Scheme::Bgn), "biysk");
+    }
+
+    #[test]
+    fn soft_and_hard_signs_are_dropped() {
+        assert_eq!(romanize("Лукьяненко", Scheme::Common), "lukyanenko");
+        assert_eq!(romanize("Лукьяненко", Scheme::J), "lukjanenko");
+        assert_eq!(romanize("Подъезд", Scheme::Common), "podezd");
+    }
+
+    #[test]
+    fn variants_are_deduplicated() {
+        assert_eq!(romanize_variants("Толстой"), ["tolstoy", "tolstoj"]);
+        assert_eq!(
+            romanize_variants("Достоевский"),
+            ["dostoevsky", "dostoevskiy", "dostoevskij"]
+        );
+        assert_eq!(romanize_variants("Мама"), ["mama"]);
+    }
+
+    #[test]
+    fn latin_text_is_only_lowercased() {
+        assert_eq!(romanize_variants("The Hobbit"), ["the hobbit"]);
+        assert_eq!(romanize_variants(""), [""]);
+        assert_eq!(romanize_variants("Generation П"), ["generation p"]);
+    }
+}